@@ -2,7 +2,7 @@ use std::io::*;
 use std::process::*;
 use std::fs::File;
 
-use chess::game::{Board, Move, get_possible_moves};
+use chess::game::{Board, Colour, Outcome, get_possible_moves};
 use chrono::Local;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -24,6 +24,8 @@ impl GameResult {
 
 fn main() {
 
+    chess::game::load_move_boards();
+
     println!("{}", (std::env::current_dir().unwrap()).display());
     
     let p1_name = "chess-current-5s";
@@ -91,11 +93,17 @@ fn run(
     let mut p1_turn = true;
     
     let mut board = Board::default();
+    let mut long_an_moves = Vec::new();
     
     loop {
         
-        if board.turns_taken == 200 {
-            return GameResult::Draw;
+        // mate and stalemate come before the draw rules, a mate on the hundredth half-move still wins
+        match board.get_outcome(&get_possible_moves(&board)) {
+            Some(Outcome::Win(Colour::White)) => return GameResult::Win,
+            Some(Outcome::Win(Colour::Black)) => return GameResult::Loss,
+            Some(Outcome::Draw) => return GameResult::Draw,
+            None if board.is_draw() => return GameResult::Draw,
+            None => {}
         }
         
         if p1_turn {
@@ -110,7 +118,14 @@ fn run(
             (&mut player2_reader, &mut player2_writer)
         };
         
-        writeln!(writer, "position fen {}", board.get_as_fen());
+        // send the whole game so engines can see repetitions
+        if long_an_moves.is_empty() {
+            writeln!(writer, "position startpos");
+        }
+        else {
+            writeln!(writer, "position startpos moves {}", long_an_moves.join(" "));
+        }
+        
         writeln!(writer, "go");
         writer.flush();
         
@@ -127,17 +142,25 @@ fn run(
         }
         else {
             
-            let long_an = response.chars()
-                .skip("bestmove ".len())
-                .take("a1b1".len())
-                .collect::<String>();
+            let long_an = response.split_whitespace().nth(1).unwrap_or("").to_string();
         
-            let move_to_make = Move::from_long_an(&long_an, &board);
-            let possible_moves = get_possible_moves(&board);
+            // an illegal move loses on the spot, and passing isn't a move in a real game
+            let move_to_make = match board.parse_uci_move(&long_an) {
+                Ok(valid_move) if !valid_move.is_null() => valid_move,
+                Ok(_) => {
+                    println!("{} played a null move", if p1_turn {"p1"} else {"p2"});
+                    return if p1_turn {GameResult::Loss} else {GameResult::Win};
+                }
+                Err(err) => {
+                    println!("{} played {}: {}", if p1_turn {"p1"} else {"p2"}, long_an, err);
+                    return if p1_turn {GameResult::Loss} else {GameResult::Win};
+                }
+            };
             
-            board.make_move(&move_to_make);
+            let move_an = board.san(&move_to_make);
             
-            let move_an = move_to_make.to_an(&possible_moves);
+            board.make_move(&move_to_make);
+            long_an_moves.push(long_an);
             
             moves.push_str(&move_an);
             moves.push(' ');
@@ -146,10 +169,6 @@ fn run(
             
         }
         
-        if board.is_draw_by_insufficient_material() {
-            return GameResult::Draw;
-        }
-        
         p1_turn = !p1_turn;
         
     }
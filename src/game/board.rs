@@ -2,149 +2,447 @@
 use super::piece::*;
 use super::r#move::*;
 use super::chess_util::*;
+use super::validate::*;
+use super::variant::*;
+use super::movegen::{is_in_check, get_possible_moves};
+use crate::hash::Zobrist;
 
-fn replace_vec<T>(vec: &mut [T], val: T, new_val: T)
-where T: PartialEq {
-    let idx = vec.iter().position(|x| *x == val).unwrap();
-    vec[idx] = new_val;
-}
-
-fn del_vec<T>(vec: &mut Vec<T>, val: T)
-where T: PartialEq {
-    let idx = vec.iter().position(|x| *x == val).unwrap();
-    vec.remove(idx);
-}
-
+#[derive(Clone)]
 pub struct Board {
     board: [Piece; 128],
+    piece_boards: [Bitboard; 16],
+    colour_boards: [Bitboard; 2],
     pub side_to_move: Colour,
     pub turns_taken: u32,
-    pub previous_moves: Vec<Move>,
+    pub halfmove_clock: u32,
     pub en_passant_chance: Option<Square>,
     pub castling_rights: CastlingRights,
-    pub piece_positions: [Vec<Square>; 16],
+    castling_rooks: [Square; 4],
+    pub chess960: bool,
     pub white_king: Square,
-    pub black_king: Square
+    pub black_king: Square,
+    pub variant: Variant,
+    pub(super) remaining_checks: [u8; 2],
+    exploded_pieces: Vec<(Square, Piece)>,
+    pub(super) pockets: [u8; 16],
+    pub(super) promoted: Bitboard,
+    hash: u64,
+    undo_stack: Vec<UndoState>
+}
+
+// what a move overwrites, one per ply so undo_move can put it all back
+#[derive(Clone, Copy, Debug)]
+struct UndoState {
+    played_move: Move,
+    castling_rights: CastlingRights,
+    halfmove_clock: u32,
+    en_passant_chance: Option<Square>,
+    remaining_checks: [u8; 2],
+    promoted: Bitboard,
+    // how many of exploded_pieces this move blew up
+    explosion_size: u8,
+    hash: u64
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FenError {
+    MissingField,
+    BadPiece(char),
+    BadRankLength(usize),
+    BadRankCount,
+    MissingKing(Colour),
+    BadSideToMove,
+    BadCastling(char),
+    BadEnPassant,
+    BadHalfmoveClock,
+    BadFullmoveNumber,
+    BadCheckCount,
+    BadPocket(char),
+    IllegalPosition(PositionProblem)
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingField => write!(f, "fen is missing fields"),
+            Self::BadPiece(c) => write!(f, "'{}' is not a piece", c),
+            Self::BadRankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+            Self::BadRankCount => write!(f, "board does not have 8 ranks"),
+            Self::MissingKing(colour) => write!(f, "{:?} has no king", colour),
+            Self::BadSideToMove => write!(f, "side to move must be 'w' or 'b'"),
+            Self::BadCastling(c) => write!(f, "'{}' is not a castling right", c),
+            Self::BadEnPassant => write!(f, "bad en passant square"),
+            Self::BadHalfmoveClock => write!(f, "bad halfmove clock"),
+            Self::BadFullmoveNumber => write!(f, "bad fullmove number"),
+            Self::BadCheckCount => write!(f, "bad three-check counter"),
+            Self::BadPocket(c) => write!(f, "'{}' can't be in a crazyhouse pocket", c),
+            Self::IllegalPosition(problem) => write!(f, "illegal position: {}", problem)
+        }
+    }
 }
 
+impl std::error::Error for FenError {}
+
 impl std::default::Default for Board {
     fn default() -> Self {
         Self::from_fen(String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"))
     }
 }
 
+impl std::str::FromStr for Board {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, FenError> {
+        Self::parse_fen(fen)
+    }
+}
+
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.get_fen())
+    }
+}
+
 impl Board {
 
+    // panics on a malformed fen, use parse_fen for input that isn't trusted
     pub fn from_fen(f: String) -> Self {
+        match Self::parse_fen(&f) {
+            Ok(board) => board,
+            Err(err) => panic!("invalid fen \"{}\": {}", f, err)
+        }
+    }
 
-        let mut chars = f.chars(); 
-        let mut setup_board = [Empty; 128];
-        
-        let mut pos = A8 as usize;
+    // like parse_fen, but also rejects well formed positions that validate() finds problems with
+    pub fn parse_fen_strict(fen: &str) -> Result<Self, FenError> {
+        Self::parse_fen_variant_strict(fen, Variant::Standard)
+    }
 
-        let mut white_king = 0;
-        let mut black_king = 0;
+    pub fn parse_fen_variant_strict(fen: &str, variant: Variant) -> Result<Self, FenError> {
 
-        let mut piece_positions = array_init::array_init(|_| Vec::new());
+        let board = Self::parse_fen_variant(fen, variant)?;
 
-        while pos <= H1 as usize {
+        match board.validate().first() {
+            Some(&problem) => Err(FenError::IllegalPosition(problem)),
+            None => Ok(board)
+        }
+    }
 
-            let c = chars.next().unwrap();
+    pub fn parse_fen(fen: &str) -> Result<Self, FenError> {
+        Self::parse_fen_variant(fen, Variant::Standard)
+    }
 
-            if c.is_alphabetic() {
+    pub fn from_fen_variant(fen: &str, variant: Variant) -> Self {
+        match Self::parse_fen_variant(fen, variant) {
+            Ok(board) => board,
+            Err(err) => panic!("invalid {} fen \"{}\": {}", variant, fen, err)
+        }
+    }
 
-                let piece = Piece::from_char(c);
+    pub fn parse_fen_variant(fen: &str, variant: Variant) -> Result<Self, FenError> {
 
-                setup_board[pos] = piece;
+        let mut fields = fen.split_whitespace().peekable();
 
-                match piece {
-                    WhiteKing => white_king = pos as Square,
-                    BlackKing => black_king = pos as Square,
-                    _ => {}
-                };
+        let placement = fields.next().ok_or(FenError::MissingField)?;
+        let side_to_move_field = fields.next().ok_or(FenError::MissingField)?;
+        let castling_field = fields.next().ok_or(FenError::MissingField)?;
+        let en_passant_field = fields.next().ok_or(FenError::MissingField)?;
 
-                piece_positions[piece as usize].push(pos as Square);
+        // three-check puts the checks each side has left after the en passant square
+        let remaining_checks_field = if variant == Variant::ThreeCheck && fields.peek().is_some_and(|field| field.contains('+')) {
+            fields.next()
+        }
+        else {
+            None
+        };
 
-                pos += 1;
-                
+        // the move counters are often left off
+        let halfmove_field = fields.next().unwrap_or("0");
+        let fullmove_field = fields.next().unwrap_or("1");
+
+        // lichess instead counts the checks given at the very end
+        let checks_given_field = if variant == Variant::ThreeCheck {fields.next()} else {None};
+
+        // crazyhouse pockets go in brackets after the board, or as a ninth rank
+        let (placement, pocket_field) = if variant != Variant::Crazyhouse {
+            (placement, None)
+        }
+        else if let Some((ranks, pocket)) = placement.strip_suffix(']').and_then(|placement| placement.split_once('[')) {
+            (ranks, Some(pocket))
+        }
+        else if placement.matches('/').count() == 8 {
+            placement.rsplit_once('/').map(|(ranks, pocket)| (ranks, Some(pocket))).unwrap()
+        }
+        else {
+            (placement, None)
+        };
+
+        let mut setup_board = [Empty; 128];
+        let mut promoted = 0;
+        let mut rank_count = 0;
+
+        for (row, rank) in placement.split('/').enumerate() {
+
+            if row >= 8 {
+                return Err(FenError::BadRankCount);
             }
-            else if c.is_numeric() {
-                for _ in 0..c.to_digit(10).unwrap() as usize {
-                    setup_board[pos] = Empty;
-                    pos += 1;
+
+            let mut col = 0;
+
+            for c in rank.chars() {
+
+                // a crazyhouse piece that started as a pawn
+                if c == '~' && pocket_field.is_some() && (1..=8).contains(&col) {
+                    promoted |= square_bitboard(row_col_to_square(row, col - 1));
+                    continue;
+                }
+
+                if let Some(spaces) = c.to_digit(10) {
+                    col += spaces as usize;
+                }
+                else {
+
+                    let piece = Piece::try_from_char(c).ok_or(FenError::BadPiece(c))?;
+
+                    if col < 8 {
+                        setup_board[row_col_to_square(row, col) as usize] = piece;
+                    }
+
+                    col += 1;
+
+                }
+
+                if col > 8 {
+                    return Err(FenError::BadRankLength(8 - row));
                 }
             }
-            else {
-                assert_eq!(c, '/');
-                pos += 8;
+
+            if col != 8 {
+                return Err(FenError::BadRankLength(8 - row));
             }
-        }
 
-        chars.next();
-        let side_to_move = Colour::from_char(chars.next().unwrap());
-        chars.next();
+            rank_count += 1;
 
-        let mut castling_rights = NO_CASTLING_RIGHTS;
+        }
 
-        let mut c;
+        if rank_count != 8 {
+            return Err(FenError::BadRankCount);
+        }
 
-        loop {
-            c = chars.next().unwrap();
-            match c {
-                'K' => castling_rights |= WHITE_KINGSIDE,
-                'Q' => castling_rights |= WHITE_QUEENSIDE,
-                'k' => castling_rights |= BLACK_KINGSIDE,
-                'q' => castling_rights |= BLACK_QUEENSIDE,
-                ' ' => break,
-                _ => {}
+        // an atomic game can finish with a king blown up
+        for colour in [White, Black] {
+            if variant.has_king(colour) && variant != Variant::Atomic && !setup_board.contains(&(colour as u8 | KING).into()) {
+                return Err(FenError::MissingKing(colour));
             }
         }
 
-        let c = chars.next().unwrap();
-        
-        let en_passant_chance = (c != '-').then(|| {
-            let mut index = String::new();
-            index.push(c);
-            index.push(chars.next().unwrap());
-            an_to_square(index)
-        });
+        let side_to_move = match side_to_move_field {
+            "w" => White,
+            "b" => Black,
+            _ => return Err(FenError::BadSideToMove)
+        };
 
-        chars.next();
+        let en_passant_chance = if en_passant_field == "-" {
+            None
+        }
+        else {
 
-        let mut fifty_turn_count = "".to_owned();
+            let en_passant_rank = match side_to_move {
+                White => '6',
+                Black => '3'
+            };
 
-        for char in chars.by_ref() {
-            if char == ' ' {
-                break;
+            let mut chars = en_passant_field.chars();
+
+            let en_passant_square = match (chars.next(), chars.next(), chars.next()) {
+                (Some('a'..='h'), Some(rank), None) if rank == en_passant_rank => an_to_square(en_passant_field.to_string()),
+                _ => return Err(FenError::BadEnPassant)
+            };
+
+            // the pawn that just moved two squares has to be in front of it, with nothing behind
+            let pushed_pawn: Piece = (side_to_move.opposite() as u8 | PAWN).into();
+            let pushed_square = side_to_move.opposite().offset_rank(en_passant_square);
+            let start_square = side_to_move.offset_rank(en_passant_square);
+
+            if setup_board[pushed_square as usize] != pushed_pawn || setup_board[en_passant_square as usize] != Empty || setup_board[start_square as usize] != Empty {
+                return Err(FenError::BadEnPassant);
+            }
+
+            Some(en_passant_square)
+        };
+
+        let halfmove_clock = halfmove_field.parse::<u32>().map_err(|_| FenError::BadHalfmoveClock)?;
+
+        // turns_taken has to fit too, with room left over for the moves played after it
+        let turns_taken = match fullmove_field.parse::<u32>() {
+            Ok(fullturns) if fullturns > 0 => (fullturns - 1).checked_mul(2)
+                .and_then(|turns| turns.checked_add(if side_to_move == White {0} else {1}))
+                .filter(|&turns| turns <= u32::MAX / 2)
+                .ok_or(FenError::BadFullmoveNumber)?,
+            _ => return Err(FenError::BadFullmoveNumber)
+        };
+
+        let remaining_checks = match (remaining_checks_field, checks_given_field) {
+            (Some(field), _) => parse_check_counts(field)?,
+            (None, Some(field)) => {
+                let [white_given, black_given] = parse_check_counts(field.trim_start_matches('+'))?;
+                [3u8.saturating_sub(white_given), 3u8.saturating_sub(black_given)]
+            }
+            (None, None) => [3, 3]
+        };
+
+        let mut board = Self::empty();
+        board.variant = variant;
+        board.remaining_checks = remaining_checks;
+        board.promoted = promoted;
+
+        for c in pocket_field.unwrap_or("").chars() {
+
+            let piece = Piece::try_from_char(c).filter(|piece| !piece.is_king()).ok_or(FenError::BadPocket(c))?;
+            board.pockets[piece as usize] += 1;
+
+            if board.pockets[piece as usize] > 16 {
+                return Err(FenError::BadPocket(c));
+            }
+        }
+
+        for square in VALID_SQUARES {
+            if setup_board[square as usize] != Empty {
+                board.put_piece(square, setup_board[square as usize]);
             }
-            fifty_turn_count += char.to_string().as_str();
         }
 
-        let mut fullturn_num = "".to_owned();
+        board.side_to_move = side_to_move;
+        board.turns_taken = turns_taken;
+        board.halfmove_clock = halfmove_clock;
+        board.en_passant_chance = en_passant_chance;
 
-        for char in chars.by_ref() {
-            if char == ' ' {
-                break;
+        // accepts KQkq (x-fen, the outermost rook) as well as shredder-fen rook files
+        if castling_field != "-" {
+            for c in castling_field.chars() {
+
+                let colour = if c.is_ascii_uppercase() {White} else {Black};
+
+                match c.to_ascii_lowercase() {
+                    'k' => board.add_castling_right(get_castling_right(colour, true), None),
+                    'q' => board.add_castling_right(get_castling_right(colour, false), None),
+                    file @ 'a'..='h' => {
+
+                        let rook_square = back_rank_square(colour, file as u8 - b'a');
+                        let kingside = rook_square % 16 > board.get_king_square(colour) % 16;
+
+                        board.add_castling_right(get_castling_right(colour, kingside), Some(rook_square));
+                        board.chess960 = true;
+
+                    }
+                    _ => return Err(FenError::BadCastling(c))
+                };
             }
-            fullturn_num += char.to_string().as_str();
         }
 
+        board.refresh_hash();
+
+        Ok(board)
+    }
+
+    pub fn get_remaining_checks(&self, colour: Colour) -> u8 {
+        self.remaining_checks[colour as usize >> 3]
+    }
+
+    // how many of a piece its side has in hand to drop
+    pub fn get_pocket_count(&self, piece: Piece) -> u8 {
+        self.pockets[piece as usize]
+    }
+
+    // a promoted piece goes back to being a pawn when it's captured in crazyhouse
+    pub fn is_promoted(&self, square: Square) -> bool {
+        self.promoted & square_bitboard(square) != 0
+    }
+
+    fn add_to_pocket(&mut self, piece: Piece) {
+        let zobrist = Zobrist::shared();
+        self.hash ^= zobrist.get_pocket_hash(piece, self.pockets[piece as usize]);
+        self.pockets[piece as usize] += 1;
+        self.hash ^= zobrist.get_pocket_hash(piece, self.pockets[piece as usize]);
+    }
+
+    fn take_from_pocket(&mut self, piece: Piece) {
+        let zobrist = Zobrist::shared();
+        self.hash ^= zobrist.get_pocket_hash(piece, self.pockets[piece as usize]);
+        self.pockets[piece as usize] -= 1;
+        self.hash ^= zobrist.get_pocket_hash(piece, self.pockets[piece as usize]);
+    }
+
+    // a board with nothing on it, for the fen parser and BoardBuilder to fill in
+    pub(super) fn empty() -> Self {
         Self {
-            board: setup_board,
-            side_to_move,
-            turns_taken: match fullturn_num.parse::<u32>() {
-                Ok(fullturns) => fullturns * 2 - 2 + if side_to_move == White {0} else {1},
-                Err(_) => 0
-            },
-            previous_moves: Vec::new(),
-            en_passant_chance,
-            castling_rights,
-            piece_positions,
-            white_king,
-            black_king
+            board: [Empty; 128],
+            piece_boards: [0; 16],
+            colour_boards: [0; 2],
+            side_to_move: White,
+            turns_taken: 0,
+            halfmove_clock: 0,
+            en_passant_chance: None,
+            castling_rights: NO_CASTLING_RIGHTS,
+            castling_rooks: DEFAULT_CASTLING_ROOKS,
+            chess960: false,
+            white_king: NO_SQUARE,
+            black_king: NO_SQUARE,
+            variant: Variant::Standard,
+            remaining_checks: [3, 3],
+            exploded_pieces: Vec::new(),
+            pockets: [0; 16],
+            promoted: 0,
+            hash: 0,
+            undo_stack: Vec::new()
+        }
+    }
+
+    // without a rook square this castles with the outermost rook, like x-fen's KQkq
+    pub(super) fn add_castling_right(&mut self, castling_right: CastlingRights, rook_square: Option<Square>) {
+
+        let rook_square = rook_square
+            .or_else(|| self.find_outer_rook(castling_right))
+            .unwrap_or(DEFAULT_CASTLING_ROOKS[castling_rook_index(castling_right)]);
+
+        let colour = if castling_right & (WHITE_KINGSIDE | WHITE_QUEENSIDE) != NO_CASTLING_RIGHTS {White} else {Black};
+
+        self.castling_rights |= castling_right;
+        self.castling_rooks[castling_rook_index(castling_right)] = rook_square;
+
+        if rook_square != DEFAULT_CASTLING_ROOKS[castling_rook_index(castling_right)] || self.get_king_square(colour) != back_rank_square(colour, 4) {
+            self.chess960 = true;
         }
     }
 
+    // for after the public fields have been set directly
+    pub(super) fn refresh_hash(&mut self) {
+        self.hash = Zobrist::shared().get_board_hash(self);
+    }
+
+    // the rook furthest towards the corner on the king's side, which x-fen's K and Q refer to
+    fn find_outer_rook(&self, castling_right: CastlingRights) -> Option<Square> {
+
+        let colour = if castling_right & (WHITE_KINGSIDE | WHITE_QUEENSIDE) != NO_CASTLING_RIGHTS {White} else {Black};
+        let rook = (colour as u8 | ROOK).into();
+        let king_file = self.get_king_square(colour) % 16;
+
+        let mut files: Vec<u8> = if castling_right & (WHITE_KINGSIDE | BLACK_KINGSIDE) != NO_CASTLING_RIGHTS {
+            (king_file + 1..8).rev().collect()
+        }
+        else {
+            (0..king_file).collect()
+        };
+
+        files.retain(|&file| self.get_piece(back_rank_square(colour, file)) == rook);
+        files.first().map(|&file| back_rank_square(colour, file))
+
+    }
+
+    pub fn get_castling_rook(&self, castling_right: CastlingRights) -> Square {
+        self.castling_rooks[castling_rook_index(castling_right)]
+    }
+
     pub fn get_fen(&self) -> String {
         
         let mut fen = "".to_owned();
@@ -172,6 +470,9 @@ impl Board {
 
                         fen += &piece.to_char().to_string();
 
+                        if self.variant == Variant::Crazyhouse && self.is_promoted(row_col_to_square(row, col)) {
+                            fen += "~";
+                        }
                     }
                 }
             }
@@ -181,6 +482,20 @@ impl Board {
             }
         }
 
+        if self.variant == Variant::Crazyhouse {
+
+            fen += "[";
+
+            for piece in POCKET_PIECES {
+                for _ in 0..self.get_pocket_count(piece) {
+                    fen.push(piece.to_char());
+                }
+            }
+
+            fen += "]";
+
+        }
+
         fen += " ";
         fen +=  &self.side_to_move.to_char().to_string();
 
@@ -190,20 +505,22 @@ impl Board {
             fen += "-";
         }
 
-        if self.castling_rights & WHITE_KINGSIDE != NO_CASTLING_RIGHTS {
-            fen += "K";
-        }
+        for (castling_right, right_char) in CASTLING_RIGHTS.into_iter().zip(['K', 'Q', 'k', 'q']) {
 
-        if self.castling_rights & WHITE_QUEENSIDE != NO_CASTLING_RIGHTS {
-            fen += "Q";
-        }
+            if self.castling_rights & castling_right == NO_CASTLING_RIGHTS {
+                continue;
+            }
 
-        if self.castling_rights & BLACK_KINGSIDE != NO_CASTLING_RIGHTS {
-            fen += "k";
-        }
+            let rook_square = self.get_castling_rook(castling_right);
 
-        if self.castling_rights & BLACK_QUEENSIDE != NO_CASTLING_RIGHTS {
-            fen += "q";
+            // x-fen, only name the rook's file when KQkq would be ambiguous
+            if self.chess960 && self.find_outer_rook(castling_right) != Some(rook_square) {
+                let file_char = (b'a' + rook_square % 16) as char;
+                fen.push(if right_char.is_uppercase() {file_char.to_ascii_uppercase()} else {file_char});
+            }
+            else {
+                fen.push(right_char);
+            }
         }
 
         fen += " ";
@@ -213,9 +530,15 @@ impl Board {
             None => String::from("-")
         }.as_str();
 
-        fen += " 0 ";
+        if self.variant == Variant::ThreeCheck {
+            fen += &format!(" {}+{}", self.get_remaining_checks(White), self.get_remaining_checks(Black));
+        }
+
+        fen += " ";
+        fen += self.halfmove_clock.to_string().as_str();
+        fen += " ";
 
-        fen + (self.turns_taken / 2 + 1).to_string().as_str() // implement 50 turn rule someday
+        fen + (self.turns_taken / 2 + 1).to_string().as_str()
 
     }
 
@@ -232,222 +555,427 @@ impl Board {
     }
     
     pub fn set_piece(&mut self, square: Square, piece: Piece) {
+
+        if self.get_piece(square) != Empty {
+            self.remove_piece(square);
+        }
+
+        if piece != Empty {
+            self.put_piece(square, piece);
+        }
+    }
+
+    fn put_piece(&mut self, square: Square, piece: Piece) {
+
+        let square_board = square_bitboard(square);
+
         self.board[square as usize] = piece;
+        self.piece_boards[piece as usize] |= square_board;
+        self.colour_boards[piece.get_colour() as usize >> 3] |= square_board;
+        self.hash ^= Zobrist::shared().get_piece_hash(piece, square);
+
+        match piece {
+            WhiteKing => self.white_king = square,
+            BlackKing => self.black_king = square,
+            _ => {}
+        }
     }
 
-    pub fn get_piece_position(&self, piece: Piece) -> &[Square] {
-        unsafe {
-            self.piece_positions.get_unchecked(piece as usize)
+    fn remove_piece(&mut self, square: Square) -> Piece {
+
+        let piece = self.get_piece(square);
+        let square_board = square_bitboard(square);
+
+        match piece {
+            WhiteKing if self.white_king == square => self.white_king = NO_SQUARE,
+            BlackKing if self.black_king == square => self.black_king = NO_SQUARE,
+            _ => {}
         }
+
+        self.board[square as usize] = Empty;
+        self.piece_boards[piece as usize] &= !square_board;
+        self.colour_boards[piece.get_colour() as usize >> 3] &= !square_board;
+        self.hash ^= Zobrist::shared().get_piece_hash(piece, square);
+
+        piece
+
+    }
+
+    fn move_piece(&mut self, start_square: Square, end_square: Square) {
+        let piece = self.remove_piece(start_square);
+        self.put_piece(end_square, piece);
     }
 
-    pub fn get_piece_position_mut(&mut self, piece: Piece) -> &mut Vec<Square> {
+    pub fn get_piece_bitboard(&self, piece: Piece) -> Bitboard {
         unsafe {
-            self.piece_positions.get_unchecked_mut(piece as usize)
+            *self.piece_boards.get_unchecked(piece as usize)
+        }
+    }
+
+    pub fn get_piece_squares(&self, piece: Piece) -> BitboardSquares {
+        bitboard_squares(self.get_piece_bitboard(piece))
+    }
+
+    pub fn get_colour_bitboard(&self, colour: Colour) -> Bitboard {
+        self.colour_boards[colour as usize >> 3]
+    }
+
+    pub fn get_occupied_bitboard(&self) -> Bitboard {
+        self.colour_boards[0] | self.colour_boards[1]
+    }
+
+    // NO_SQUARE for a side without a king, like horde's white or an exploded atomic king
+    pub fn get_king_square(&self, colour: Colour) -> Square {
+        match colour {
+            White => self.white_king,
+            Black => self.black_king
         }
     }
 
     pub fn get_piece_counts(&self, colour: Colour) -> [u32; 6] {
         [
-            self.get_piece_position((colour as u8 | PAWN).into()).len() as u32,
-            self.get_piece_position((colour as u8 | KNIGHT).into()).len() as u32,
-            self.get_piece_position((colour as u8 | BISHOP).into()).len() as u32,
-            self.get_piece_position((colour as u8 | ROOK).into()).len() as u32,
-            self.get_piece_position((colour as u8 | QUEEN).into()).len() as u32,
+            self.get_piece_bitboard((colour as u8 | PAWN).into()).count_ones(),
+            self.get_piece_bitboard((colour as u8 | KNIGHT).into()).count_ones(),
+            self.get_piece_bitboard((colour as u8 | BISHOP).into()).count_ones(),
+            self.get_piece_bitboard((colour as u8 | ROOK).into()).count_ones(),
+            self.get_piece_bitboard((colour as u8 | QUEEN).into()).count_ones(),
             1
         ]
     }
     
     pub fn is_draw_by_insufficient_material(&self) -> bool {
-        self.piece_positions[WhitePawn as usize].is_empty() && 
-        self.piece_positions[WhiteRook as usize].is_empty() && 
-        self.piece_positions[WhiteQueen as usize].is_empty() && 
-        self.piece_positions[WhiteBishop as usize].len() + self.piece_positions[WhiteKnight as usize].len() <= 1 && 
-        self.piece_positions[BlackPawn as usize].is_empty() && 
-        self.piece_positions[BlackRook as usize].is_empty() && 
-        self.piece_positions[BlackQueen as usize].is_empty() && 
-        self.piece_positions[BlackBishop as usize].len() + self.piece_positions[WhiteKnight as usize].len() <= 1
+
+        // anything in hand can still be dropped
+        if self.pockets.iter().any(|&count| count != 0) {
+            return false;
+        }
+
+        let heavy_pieces = self.get_piece_bitboard(WhitePawn) | self.get_piece_bitboard(WhiteRook) | self.get_piece_bitboard(WhiteQueen) |
+            self.get_piece_bitboard(BlackPawn) | self.get_piece_bitboard(BlackRook) | self.get_piece_bitboard(BlackQueen);
+
+        heavy_pieces == 0 &&
+        (self.get_piece_bitboard(WhiteBishop) | self.get_piece_bitboard(WhiteKnight)).count_ones() <= 1 &&
+        (self.get_piece_bitboard(BlackBishop) | self.get_piece_bitboard(BlackKnight)).count_ones() <= 1
+    }
+
+    // a mate on the hundredth half-move still wins
+    pub fn is_draw_by_fifty_moves(&self) -> bool {
+        self.halfmove_clock >= 100 && !(self.is_in_check() && get_possible_moves(self).is_empty())
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // only positions since the last irreversible move can repeat, so the
+    // halfmove clock bounds how far back the key history is searched.
+    // a null move isn't a real move either, so nothing before one counts
+    pub fn is_repetition(&self, count: u32) -> bool {
+
+        let current_idx = self.undo_stack.len();
+        let earliest_idx = current_idx.saturating_sub(self.halfmove_clock as usize);
+
+        let mut occurrences = 1;
+        let mut idx = current_idx;
+
+        while idx >= earliest_idx + 2 {
+
+            if self.undo_stack[idx - 2..idx].iter().any(|state| state.played_move.is_null()) {
+                break;
+            }
+
+            idx -= 2;
+
+            if self.undo_stack[idx].hash == self.hash {
+                occurrences += 1;
+                if occurrences >= count {
+                    return true;
+                }
+            }
+        }
+
+        occurrences >= count
+
+    }
+
+    pub fn previous_moves(&self) -> impl DoubleEndedIterator<Item = &Move> + ExactSizeIterator {
+        self.undo_stack.iter().map(|state| &state.played_move)
+    }
+
+    pub fn last_move(&self) -> Option<&Move> {
+        self.undo_stack.last().map(|state| &state.played_move)
+    }
+
+    fn save_undo_state(&self, played_move: &Move) -> UndoState {
+        UndoState {
+            played_move: *played_move,
+            castling_rights: self.castling_rights,
+            halfmove_clock: self.halfmove_clock,
+            en_passant_chance: self.en_passant_chance,
+            remaining_checks: self.remaining_checks,
+            promoted: self.promoted,
+            explosion_size: 0,
+            hash: self.hash
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.is_repetition(3) || self.is_draw_by_fifty_moves() || self.is_draw_by_insufficient_material()
     }
 
     pub fn make_move(&mut self, move_to_make: &Move) {
 
+        if move_to_make.move_type == MoveType::Null {
+            return self.make_null_move();
+        }
+
         let move_colour = self.side_to_move;
         let opp_colour = move_colour.opposite();
+
+        let old_castling_rights = self.castling_rights;
+        let mut undo_state = self.save_undo_state(move_to_make);
+
+        if self.variant == Variant::Crazyhouse {
+            self.update_pockets(move_to_make);
+        }
+
+        if move_to_make.replaced_piece != Empty {
+            self.remove_piece(move_to_make.end_square);
+        }
         
         match move_to_make.move_type {
+            MoveType::Drop(piece) => self.put_piece(move_to_make.end_square, piece),
             MoveType::EnPassant => {
-                
-                self.set_piece(move_to_make.start_square, Empty);
-                self.set_piece(move_to_make.end_square, move_to_make.moved_piece);
-            
-                replace_vec(self.get_piece_position_mut(move_to_make.moved_piece), move_to_make.start_square, move_to_make.end_square);
-                
-                let captured_square = opp_colour.offset_rank(move_to_make.end_square);
-                let captured_piece = self.get_piece(captured_square);
-                self.set_piece(captured_square, Empty);
-                del_vec(self.get_piece_position_mut(captured_piece), captured_square);
-                
+                self.move_piece(move_to_make.start_square, move_to_make.end_square);
+                self.remove_piece(opp_colour.offset_rank(move_to_make.end_square));
             }
             MoveType::Promotion(promote_to) => {
-                
-                self.set_piece(move_to_make.start_square, Empty);
-                self.set_piece(move_to_make.end_square, promote_to);
-                
-                del_vec(self.get_piece_position_mut((move_colour as u8 | PAWN).into()), move_to_make.start_square);
-                self.get_piece_position_mut(promote_to).push(move_to_make.end_square);
-                
-                if move_to_make.replaced_piece != Empty {
-                    del_vec(self.get_piece_position_mut(move_to_make.replaced_piece), move_to_make.end_square);
-                }
+                self.remove_piece(move_to_make.start_square);
+                self.put_piece(move_to_make.end_square, promote_to);
             }
             MoveType::Castle => {
-                
-                let rook = (move_colour as u8 | ROOK).into();
-                let (rook_start_square, rook_end_square) = if move_to_make.end_square % 16 < 4 {
-                    (move_to_make.end_square - 2, move_to_make.end_square + 1)
-                }
-                else {
-                    (move_to_make.end_square + 1, move_to_make.end_square - 1)
-                };
-                
-                self.set_piece(move_to_make.start_square, Empty);
-                self.set_piece(move_to_make.end_square, move_to_make.moved_piece);
-                self.set_piece(rook_start_square, Empty);
-                self.set_piece(rook_end_square, rook);
-                
-                replace_vec(self.get_piece_position_mut(move_to_make.moved_piece), move_to_make.start_square, move_to_make.end_square);
-                replace_vec(self.get_piece_position_mut(rook), rook_start_square, rook_end_square);
-                
-            }
-            _ => {
-                
-                self.set_piece(move_to_make.start_square, Empty);
-                self.set_piece(move_to_make.end_square, move_to_make.moved_piece);
-            
-                replace_vec(self.get_piece_position_mut(move_to_make.moved_piece), move_to_make.start_square, move_to_make.end_square);
-                
-                if move_to_make.replaced_piece != Empty {
-                    del_vec(self.get_piece_position_mut(move_to_make.replaced_piece), move_to_make.end_square);
-                }
+
+                // castles are encoded as the king taking its own rook
+                let castling_right = get_castling_right(move_colour, move_to_make.end_square > move_to_make.start_square);
+                let (king_end_square, rook_end_square) = get_castling_end_squares(castling_right);
+
+                let king = self.remove_piece(move_to_make.start_square);
+                let rook = self.remove_piece(move_to_make.end_square);
+                self.put_piece(king_end_square, king);
+                self.put_piece(rook_end_square, rook);
+
             }
+            _ => self.move_piece(move_to_make.start_square, move_to_make.end_square)
         }
 
         match move_to_make.moved_piece {
-            WhiteRook if move_to_make.start_square == H1 => self.castling_rights &= !WHITE_KINGSIDE,
-            WhiteRook if move_to_make.start_square == A1 => self.castling_rights &= !WHITE_QUEENSIDE,
-            BlackRook if move_to_make.start_square == H8 => self.castling_rights &= !BLACK_KINGSIDE,
-            BlackRook if move_to_make.start_square == A8 => self.castling_rights &= !BLACK_QUEENSIDE,
-            WhiteKing => {
-                self.castling_rights &= !(WHITE_KINGSIDE | WHITE_QUEENSIDE);
-                self.white_king = move_to_make.end_square;
-            }
-            BlackKing => {
-                self.castling_rights &= !(BLACK_KINGSIDE | BLACK_QUEENSIDE);
-                self.black_king = move_to_make.end_square;
-            }
+            WhiteKing => self.castling_rights &= !(WHITE_KINGSIDE | WHITE_QUEENSIDE),
+            BlackKing => self.castling_rights &= !(BLACK_KINGSIDE | BLACK_QUEENSIDE),
             _ => {}
         }
 
-        self.castling_rights &= match move_to_make.end_square {
-            H1 => !WHITE_KINGSIDE,
-            A1 => !WHITE_QUEENSIDE,
-            H8 => !BLACK_KINGSIDE,
-            A8 => !BLACK_QUEENSIDE,
-            _ => ALL_CASTLING_RIGHTS
-        };
+        // moving or capturing a castling rook
+        for castling_right in CASTLING_RIGHTS {
+            let rook_square = self.get_castling_rook(castling_right);
+            if move_to_make.start_square == rook_square || move_to_make.end_square == rook_square {
+                self.castling_rights &= !castling_right;
+            }
+        }
 
-        self.en_passant_chance = (move_to_make.move_type == MoveType::PawnDouble).then(|| opp_colour.offset_rank(move_to_make.end_square));
+        if self.variant == Variant::Atomic && move_to_make.is_capture() {
+            undo_state.explosion_size = self.explode(move_to_make.end_square);
+        }
+
+        if move_to_make.moved_piece.is_pawn() || move_to_make.replaced_piece != Empty {
+            self.halfmove_clock = 0;
+        }
+        else {
+            self.halfmove_clock += 1;
+        }
+
+        let old_en_passant_chance = self.en_passant_chance;
+        // horde pawns can push two from the back rank, but that can't be taken en passant
+        let is_first_rank = move_to_make.start_square / 16 == if move_colour == White {7} else {0};
+        self.en_passant_chance = (move_to_make.move_type == MoveType::PawnDouble && !is_first_rank).then(|| opp_colour.offset_rank(move_to_make.end_square));
         self.side_to_move = opp_colour;
-        self.previous_moves.push(*move_to_make);
+
+        let zobrist = Zobrist::shared();
+        self.hash ^= zobrist.get_en_passant_hash(old_en_passant_chance) ^ zobrist.get_en_passant_hash(self.en_passant_chance);
+        self.hash ^= zobrist.get_castling_hash(old_castling_rights) ^ zobrist.get_castling_hash(self.castling_rights);
+        self.hash ^= zobrist.get_side_to_move_hash();
+
+        if self.variant == Variant::ThreeCheck && is_in_check(self, opp_colour) {
+            self.hash ^= zobrist.get_remaining_checks_hash(move_colour, self.get_remaining_checks(move_colour));
+            self.remaining_checks[move_colour as usize >> 3] -= 1;
+            self.hash ^= zobrist.get_remaining_checks_hash(move_colour, self.get_remaining_checks(move_colour));
+        }
+
+        self.undo_stack.push(undo_state);
         self.turns_taken += 1;
 
     }
 
+    // captured pieces change sides into the capturer's pocket, and promoted pieces are followed around
+    fn update_pockets(&mut self, move_to_make: &Move) {
+
+        let move_colour = self.side_to_move;
+        let start_board = square_bitboard(move_to_make.start_square);
+        let end_board = square_bitboard(move_to_make.end_square);
+
+        if move_to_make.is_capture() {
+            let captured_code = if self.promoted & end_board != 0 {PAWN} else {move_to_make.captured_piece() as u8 & 0b111};
+            self.add_to_pocket((move_colour as u8 | captured_code).into());
+        }
+
+        self.promoted &= !end_board;
+
+        match move_to_make.move_type {
+            MoveType::Drop(piece) => self.take_from_pocket(piece),
+            MoveType::Promotion(_) => self.promoted |= end_board,
+            MoveType::Castle => {}
+            _ => if self.promoted & start_board != 0 {
+                self.promoted ^= start_board | end_board;
+            }
+        }
+    }
+
+    // an atomic capture blows up the capturing piece and every piece but a pawn next to it
+    fn explode(&mut self, square: Square) -> u8 {
+
+        let blast_board = (get_king_move_board(square) & !self.get_piece_bitboard(WhitePawn) & !self.get_piece_bitboard(BlackPawn)) | square_bitboard(square);
+        let mut explosion_size = 0;
+
+        for blast_square in bitboard_squares(blast_board & self.get_occupied_bitboard()) {
+
+            let piece = self.remove_piece(blast_square);
+            self.exploded_pieces.push((blast_square, piece));
+            explosion_size += 1;
+
+            if piece.is_king() {
+                self.castling_rights &= !(get_castling_right(piece.get_colour(), true) | get_castling_right(piece.get_colour(), false));
+            }
+
+            for castling_right in CASTLING_RIGHTS {
+                if self.get_castling_rook(castling_right) == blast_square {
+                    self.castling_rights &= !castling_right;
+                }
+            }
+        }
+
+        explosion_size
+
+    }
+
+    // the side to move passes, a null move sits in the undo stack like any other
+    pub fn make_null_move(&mut self) {
+
+        let undo_state = self.save_undo_state(&Move::new_null(self));
+
+        let zobrist = Zobrist::shared();
+        self.hash ^= zobrist.get_en_passant_hash(self.en_passant_chance) ^ zobrist.get_side_to_move_hash();
+
+        self.en_passant_chance = None;
+        self.side_to_move = self.side_to_move.opposite();
+        self.halfmove_clock += 1;
+
+        self.undo_stack.push(undo_state);
+        self.turns_taken += 1;
+
+    }
+
+    pub fn undo_null_move(&mut self) -> Option<Move> {
+
+        if !self.last_move()?.is_null() {
+            return None;
+        }
+
+        self.undo_move()
+
+    }
+
     pub fn undo_move(&mut self) -> Option<Move> {
 
         let opp_colour = self.side_to_move;
         let move_colour = opp_colour.opposite();
 
-        let Some(move_to_undo) = self.previous_moves.pop()
-        else {
-            return None;
-        };
+        let undo_state = self.undo_stack.pop()?;
+        let move_to_undo = undo_state.played_move;
+
+        for _ in 0..undo_state.explosion_size {
+            let (square, piece) = self.exploded_pieces.pop().unwrap();
+            self.put_piece(square, piece);
+        }
         
-        match move_to_undo.move_type {
-            MoveType::EnPassant => {
-                
-                self.set_piece(move_to_undo.start_square, move_to_undo.moved_piece);
-                self.set_piece(move_to_undo.end_square, Empty);
-            
-                replace_vec(self.get_piece_position_mut(move_to_undo.moved_piece), move_to_undo.end_square, move_to_undo.start_square);
-                
-                let captured_square = opp_colour.offset_rank(move_to_undo.end_square);
-                let pawn = (opp_colour as u8 | PAWN).into();
-                self.set_piece(captured_square, pawn);
-                self.get_piece_position_mut(pawn).push(captured_square);
-                
-            }
-            MoveType::Promotion(promote_to) => {
-                
-                let pawn = (move_colour as u8 | PAWN).into();
-                
-                self.set_piece(move_to_undo.start_square, pawn);
-                self.set_piece(move_to_undo.end_square, move_to_undo.replaced_piece);
-                
-                self.get_piece_position_mut(pawn).push(move_to_undo.start_square);
-                del_vec(self.get_piece_position_mut(promote_to), move_to_undo.end_square);
-                
-                if move_to_undo.replaced_piece != Empty {
-                    self.get_piece_position_mut(move_to_undo.replaced_piece).push(move_to_undo.end_square);
-                }
+        self.promoted = undo_state.promoted;
+
+        // the hash is restored wholesale below, so the pockets can be too
+        if self.variant == Variant::Crazyhouse {
+            if let MoveType::Drop(piece) = move_to_undo.move_type {
+                self.pockets[piece as usize] += 1;
             }
-            MoveType::Castle => {
-                
-                let rook = (move_colour as u8 | ROOK).into();
-                let (rook_start_square, rook_end_square) = if move_to_undo.end_square % 16 < 4 {
-                    (move_to_undo.end_square - 2, move_to_undo.end_square + 1)
+            else if move_to_undo.is_capture() {
+                let captured_code = if self.is_promoted(move_to_undo.end_square) {
+                    PAWN
                 }
                 else {
-                    (move_to_undo.end_square + 1, move_to_undo.end_square - 1)
+                    move_to_undo.captured_piece() as u8 & 0b111
                 };
-                
-                self.set_piece(move_to_undo.start_square, move_to_undo.moved_piece);
-                self.set_piece(move_to_undo.end_square, Empty);
-                self.set_piece(rook_start_square, rook);
-                self.set_piece(rook_end_square, Empty);
-                
-                replace_vec(self.get_piece_position_mut(move_to_undo.moved_piece), move_to_undo.end_square, move_to_undo.start_square);
-                replace_vec(self.get_piece_position_mut(rook), rook_end_square, rook_start_square);
-                
+                self.pockets[(move_colour as u8 | captured_code) as usize] -= 1;
             }
-            _ => {
-                
-                self.set_piece(move_to_undo.start_square, move_to_undo.moved_piece);
-                self.set_piece(move_to_undo.end_square, move_to_undo.replaced_piece);
-            
-                replace_vec(self.get_piece_position_mut(move_to_undo.moved_piece), move_to_undo.end_square, move_to_undo.start_square);
-                
-                if move_to_undo.replaced_piece != Empty {
-                    self.get_piece_position_mut(move_to_undo.replaced_piece).push(move_to_undo.end_square);
-                }
+        }
+        
+        match move_to_undo.move_type {
+            MoveType::Drop(_) => {
+                self.remove_piece(move_to_undo.end_square);
             }
+            MoveType::EnPassant => {
+                self.move_piece(move_to_undo.end_square, move_to_undo.start_square);
+                self.put_piece(opp_colour.offset_rank(move_to_undo.end_square), (opp_colour as u8 | PAWN).into());
+            }
+            MoveType::Promotion(_) => {
+                self.remove_piece(move_to_undo.end_square);
+                self.put_piece(move_to_undo.start_square, (move_colour as u8 | PAWN).into());
+            }
+            MoveType::Null => {}
+            MoveType::Castle => {
+
+                let castling_right = get_castling_right(move_colour, move_to_undo.end_square > move_to_undo.start_square);
+                let (king_end_square, rook_end_square) = get_castling_end_squares(castling_right);
+
+                let king = self.remove_piece(king_end_square);
+                let rook = self.remove_piece(rook_end_square);
+                self.put_piece(move_to_undo.start_square, king);
+                self.put_piece(move_to_undo.end_square, rook);
+
+            }
+            _ => self.move_piece(move_to_undo.end_square, move_to_undo.start_square)
         }
 
-        match move_to_undo.moved_piece {
-            WhiteKing => self.white_king = move_to_undo.start_square,
-            BlackKing => self.black_king = move_to_undo.start_square,
-            _ => {}
+        if move_to_undo.replaced_piece != Empty {
+            self.put_piece(move_to_undo.end_square, move_to_undo.replaced_piece);
         }
 
-        self.en_passant_chance = self.previous_moves.last().and_then(|prev_move| {
-            (prev_move.move_type == MoveType::PawnDouble).then(|| move_colour.offset_rank(prev_move.end_square))
-        });
-        
-        self.castling_rights = move_to_undo.old_castling_rights;
+        self.en_passant_chance = undo_state.en_passant_chance;
+        self.castling_rights = undo_state.castling_rights;
+        self.halfmove_clock = undo_state.halfmove_clock;
+        self.remaining_checks = undo_state.remaining_checks;
         self.side_to_move = move_colour;
         self.turns_taken -= 1;
 
+        // the piece moves above churned the key, the saved one is exact
+        self.hash = undo_state.hash;
+
         Some(move_to_undo)
 
     }
 }
+
+fn parse_check_counts(field: &str) -> Result<[u8; 2], FenError> {
+
+    let (white_field, black_field) = field.split_once('+').ok_or(FenError::BadCheckCount)?;
+
+    match (white_field.parse::<u8>(), black_field.parse::<u8>()) {
+        (Ok(white_checks), Ok(black_checks)) if white_checks <= 3 && black_checks <= 3 => Ok([white_checks, black_checks]),
+        _ => Err(FenError::BadCheckCount)
+    }
+}
@@ -0,0 +1,202 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::validate::*;
+use super::variant::*;
+
+// sets up a position piece by piece, nothing is checked until build()
+#[derive(Clone)]
+pub struct BoardBuilder {
+    pieces: [Piece; 128],
+    side_to_move: Colour,
+    castling: Vec<(CastlingRights, Option<Square>)>,
+    en_passant_chance: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    chess960: bool,
+    variant: Variant,
+    // crazyhouse only, indexed by piece like the board's
+    pockets: [u8; 16],
+    promoted: Bitboard,
+    // three-check only
+    remaining_checks: [u8; 2]
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+
+    pub fn new() -> Self {
+        Self {
+            pieces: [Empty; 128],
+            side_to_move: White,
+            castling: Vec::new(),
+            en_passant_chance: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: false,
+            variant: Variant::Standard,
+            pockets: [0; 16],
+            promoted: 0,
+            remaining_checks: [3, 3]
+        }
+    }
+
+    // starts from an existing position, for editing it
+    pub fn from_board(board: &Board) -> Self {
+
+        let mut builder = Self::new();
+
+        for square in VALID_SQUARES {
+            builder.pieces[square as usize] = board.get_piece(square);
+        }
+
+        for castling_right in CASTLING_RIGHTS {
+            if board.castling_rights & castling_right != NO_CASTLING_RIGHTS {
+                builder.castling.push((castling_right, Some(board.get_castling_rook(castling_right))));
+            }
+        }
+
+        builder.side_to_move = board.side_to_move;
+        builder.en_passant_chance = board.en_passant_chance;
+        builder.halfmove_clock = board.halfmove_clock;
+        builder.fullmove_number = board.turns_taken / 2 + 1;
+        builder.chess960 = board.chess960;
+        builder.variant = board.variant;
+        builder.pockets = board.pockets;
+        builder.promoted = board.promoted;
+        builder.remaining_checks = board.remaining_checks;
+
+        builder
+
+    }
+
+    pub fn piece(&mut self, square: Square, piece: Piece) -> &mut Self {
+        self.pieces[square as usize] = piece;
+        self
+    }
+
+    pub fn remove_piece(&mut self, square: Square) -> &mut Self {
+        self.pieces[square as usize] = Empty;
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.pieces = [Empty; 128];
+        self.promoted = 0;
+        self
+    }
+
+    pub fn get_piece(&self, square: Square) -> Piece {
+        self.pieces[square as usize]
+    }
+
+    pub fn side_to_move(&mut self, colour: Colour) -> &mut Self {
+        self.side_to_move = colour;
+        self
+    }
+
+    // castles with the outermost rook on that side, as in a normal game
+    pub fn castling(&mut self, castling_right: CastlingRights) -> &mut Self {
+        self.castling_rook(castling_right, None)
+    }
+
+    // picks the rook for chess960 positions with more than one on a side
+    pub fn castling_rook(&mut self, castling_right: CastlingRights, rook_square: Option<Square>) -> &mut Self {
+        self.castling.retain(|(existing_right, _)| *existing_right != castling_right);
+        self.castling.push((castling_right, rook_square));
+        self
+    }
+
+    pub fn no_castling(&mut self) -> &mut Self {
+        self.castling.clear();
+        self
+    }
+
+    pub fn en_passant(&mut self, en_passant_chance: Option<Square>) -> &mut Self {
+        self.en_passant_chance = en_passant_chance;
+        self
+    }
+
+    pub fn halfmove_clock(&mut self, halfmove_clock: u32) -> &mut Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+
+    pub fn fullmove_number(&mut self, fullmove_number: u32) -> &mut Self {
+        self.fullmove_number = fullmove_number.max(1);
+        self
+    }
+
+    pub fn chess960(&mut self, chess960: bool) -> &mut Self {
+        self.chess960 = chess960;
+        self
+    }
+
+    pub fn variant(&mut self, variant: Variant) -> &mut Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn pocket(&mut self, piece: Piece, count: u8) -> &mut Self {
+        self.pockets[piece as usize] = count;
+        self
+    }
+
+    // whether the piece on the square goes back to being a pawn when it's captured
+    pub fn promoted(&mut self, square: Square, promoted: bool) -> &mut Self {
+        if promoted {
+            self.promoted |= square_bitboard(square);
+        }
+        else {
+            self.promoted &= !square_bitboard(square);
+        }
+        self
+    }
+
+    pub fn remaining_checks(&mut self, colour: Colour, remaining_checks: u8) -> &mut Self {
+        self.remaining_checks[colour as usize >> 3] = remaining_checks;
+        self
+    }
+
+    pub fn build(&self) -> Result<Board, Vec<PositionProblem>> {
+
+        let mut board = Board::empty();
+        board.variant = self.variant;
+        board.pockets = self.pockets;
+        board.remaining_checks = self.remaining_checks;
+
+        for square in VALID_SQUARES {
+            board.set_piece(square, self.pieces[square as usize]);
+        }
+
+        // an empty square has nothing to be promoted
+        board.promoted = self.promoted & board.get_occupied_bitboard();
+
+        board.side_to_move = self.side_to_move;
+        board.turns_taken = self.fullmove_number * 2 - 2 + if self.side_to_move == White {0} else {1};
+        board.halfmove_clock = self.halfmove_clock;
+        board.en_passant_chance = self.en_passant_chance;
+
+        for &(castling_right, rook_square) in &self.castling {
+            board.add_castling_right(castling_right, rook_square);
+        }
+
+        board.chess960 |= self.chess960;
+        board.refresh_hash();
+
+        let problems = board.validate();
+
+        if problems.is_empty() {
+            Ok(board)
+        }
+        else {
+            Err(problems)
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::LazyLock;
+
 use super::Colour;
 
 pub type CastlingRights = u8;
@@ -10,8 +12,51 @@ pub const WHITE_QUEENSIDE: CastlingRights = 0b0100;
 pub const BLACK_KINGSIDE: CastlingRights = 0b0010;
 pub const BLACK_QUEENSIDE: CastlingRights = 0b0001;
 
+pub const CASTLING_RIGHTS: [CastlingRights; 4] = [WHITE_KINGSIDE, WHITE_QUEENSIDE, BLACK_KINGSIDE, BLACK_QUEENSIDE];
+
+pub fn get_castling_right(colour: Colour, kingside: bool) -> CastlingRights {
+    match (colour, kingside) {
+        (Colour::White, true) => WHITE_KINGSIDE,
+        (Colour::White, false) => WHITE_QUEENSIDE,
+        (Colour::Black, true) => BLACK_KINGSIDE,
+        (Colour::Black, false) => BLACK_QUEENSIDE
+    }
+}
+
+// indexed by castling_rook_index
+pub const DEFAULT_CASTLING_ROOKS: [Square; 4] = [A8, H8, A1, H1];
+
+pub fn castling_rook_index(castling_right: CastlingRights) -> usize {
+    castling_right.trailing_zeros() as usize
+}
+
+pub fn back_rank_square(colour: Colour, file: u8) -> Square {
+    match colour {
+        Colour::White => A1 + file,
+        Colour::Black => A8 + file
+    }
+}
+
+// king and rook destinations, which are the same in chess960
+pub fn get_castling_end_squares(castling_right: CastlingRights) -> (Square, Square) {
+    match castling_right {
+        WHITE_KINGSIDE => (G1, F1),
+        WHITE_QUEENSIDE => (C1, D1),
+        BLACK_KINGSIDE => (G8, F8),
+        _ => (C8, D8)
+    }
+}
+
 pub type Square = u8;
 
+// bitboards are indexed a8 = 0 .. h1 = 63, squares stay 0x88
+pub type Bitboard = u64;
+
+pub const BACK_RANKS: Bitboard = 0xFF000000000000FF;
+
+// off the board, where a side with no king keeps its king square
+pub const NO_SQUARE: Square = 0x88;
+
 pub const A8: Square = 0x00;
 pub const B8: Square = 0x01;
 pub const C8: Square = 0x02;
@@ -100,12 +145,95 @@ pub const KING_OFFSETS: [i8; 8] = [-17, -16, -15, -1, 1, 15, 16, 17];
 pub const ORTHOGONAL_OFFSETS: [i8; 4] = [-16, -1, 1, 16];
 pub const DIAGONAL_OFFSETS: [i8; 4] = [-17, -15, 15, 17];
 
-pub static mut WHITE_PAWN_ATTACK_BOARDS: [u128; 128] = [0; 128];
-pub static mut BLACK_PAWN_ATTACK_BOARDS: [u128; 128] = [0; 128];
-pub static mut KNIGHT_MOVE_BOARDS: [u128; 128] = [0; 128];
-pub static mut KING_MOVE_BOARDS: [u128; 128] = [0; 128];
+// found offline with a sparse random search, indexed the same way as bitboards
+const ROOK_MAGICS: [u64; 64] = [
+    0x2080002080400010, 0x00c0002001401000, 0x2100110008402002, 0x0880080081041000,
+    0x0200020020041008, 0x2300040008010012, 0x0c00283004008201, 0x0180010000407a80,
+    0x0168800080400020, 0x0010400040201000, 0x1001002001001048, 0x1001002408100100,
+    0x0801000408010012, 0x4001000209000400, 0x08a20004c8020001, 0x2002801145002280,
+    0x0080860021004200, 0x001000c009402002, 0x00b0002004002800, 0x100a808010020800,
+    0x8101010008000410, 0x0244008002000480, 0x0000040010810208, 0x2000020000448534,
+    0x4104400480008033, 0x0000810100204000, 0x0440430900200010, 0x4600240900100100,
+    0x0060080080040080, 0x0001000300080400, 0x0004084400011002, 0x0023040200008041,
+    0x0580050043002080, 0x0400804002802008, 0x0001002001004010, 0x1000200901001000,
+    0x4410800801800c00, 0xa012003806001004, 0x0020100104008802, 0x0004808402000041,
+    0x0010400170898000, 0x0080500020004004, 0x1040408012020020, 0x8010040008004040,
+    0x2001080100110004, 0x0000020004008080, 0x0021010810040002, 0x0800008c43020024,
+    0x0000800021005100, 0x0070201040008080, 0x0000d04282006a00, 0x0010014400080240,
+    0x0001080110050100, 0x0012000810240600, 0x0402000801040200, 0x028100108a004100,
+    0x0050800300102045, 0x8208210040120882, 0x8010600101183441, 0x020b000910006045,
+    0x0241001002480005, 0x0081000400880241, 0x0000009008024124, 0x0048122980410402,
+];
+
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0848020822040013, 0x8010a40085821200, 0x0008008430840822, 0x0808048108040000,
+    0x1304042100008104, 0x5001012010204023, 0x81048801b8200420, 0x200a008084012000,
+    0x0040102001042084, 0x840a505042428020, 0x0000700102202920, 0x44101c0c10800002,
+    0x0040040422000000, 0x0180020802090202, 0x4020020811041202, 0x000104308c042000,
+    0x4140661002424400, 0x0028012008010460, 0x0188062102002a00, 0x0014004840102008,
+    0x0105000290400002, 0x8001022200410400, 0x104a041918013446, 0x008a000082008238,
+    0x04a0060008100430, 0x0008220008820801, 0x2508041208005010, 0x4008080200202020,
+    0x2441001013004000, 0x0030008060407000, 0x4008108000420800, 0x0012021050290100,
+    0x0210080482200500, 0xcc01112048100480, 0x0020402806500440, 0x00048e0080580080,
+    0x0040102020020080, 0x0028010440080807, 0x4601041108008800, 0x8040810e04104200,
+    0x901210110400088a, 0xa003080212081050, 0x00c1004048401004, 0x900000a014400800,
+    0x0008021040405401, 0x4020008206002090, 0x0004190424030100, 0x0424008a02026250,
+    0x8004088250900040, 0x1c00430088a04200, 0x0001020094040001, 0x8040210020880061,
+    0x2010040450442032, 0x0800840850044001, 0x0004040802140004, 0x0004080a04222020,
+    0x8088802110022000, 0x1081a10416114400, 0x0205010a24060820, 0x0000000720411080,
+    0x1008000208430400, 0x580c026028810840, 0x802020441020a110, 0x12c0022401020018,
+];
+
+const ROOK_ATTACK_TABLE_SIZE: usize = 102400;
+const BISHOP_ATTACK_TABLE_SIZE: usize = 5248;
+
+#[derive(Clone, Copy)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize
+}
+
+const EMPTY_MAGIC: Magic = Magic {
+    mask: 0,
+    magic: 0,
+    shift: 0,
+    offset: 0
+};
+
+struct MoveBoards {
+    white_pawn_attack_boards: [Bitboard; 64],
+    black_pawn_attack_boards: [Bitboard; 64],
+    knight_move_boards: [Bitboard; 64],
+    king_move_boards: [Bitboard; 64],
+    between_boards: [[Bitboard; 64]; 64],
+    rook_magic_entries: [Magic; 64],
+    bishop_magic_entries: [Magic; 64],
+    // too big for the stack while it's being filled in
+    sliding_attack_boards: Box<[Bitboard]>
+}
+
+// built the first time anything looks a board up, so there's nothing to forget to call
+static MOVE_BOARDS: LazyLock<MoveBoards> = LazyLock::new(init_move_boards);
 
+// only saves the first lookup the time it takes to build the tables
 pub fn load_move_boards() {
+    LazyLock::force(&MOVE_BOARDS);
+}
+
+fn init_move_boards() -> MoveBoards {
+
+    let mut move_boards = MoveBoards {
+        white_pawn_attack_boards: [0; 64],
+        black_pawn_attack_boards: [0; 64],
+        knight_move_boards: [0; 64],
+        king_move_boards: [0; 64],
+        between_boards: [[0; 64]; 64],
+        rook_magic_entries: [EMPTY_MAGIC; 64],
+        bishop_magic_entries: [EMPTY_MAGIC; 64],
+        sliding_attack_boards: vec![0; ROOK_ATTACK_TABLE_SIZE + BISHOP_ATTACK_TABLE_SIZE].into_boxed_slice()
+    };
     
     for square in VALID_SQUARES {
         
@@ -118,66 +246,219 @@ pub fn load_move_boards() {
 
         if rank != 0 {
             if file != 0 {
-                white_pawn_attack_board |= 1 << row_col_to_square(rank - 1, file - 1);
+                white_pawn_attack_board |= square_bitboard(row_col_to_square(rank - 1, file - 1));
             }
             if file != 7 {
-                white_pawn_attack_board |= 1 << row_col_to_square(rank - 1, file + 1);
+                white_pawn_attack_board |= square_bitboard(row_col_to_square(rank - 1, file + 1));
             }
         }
 
         if rank != 7 {
             if file != 0 {
-                black_pawn_attack_board |= 1 << row_col_to_square(rank + 1, file - 1);
+                black_pawn_attack_board |= square_bitboard(row_col_to_square(rank + 1, file - 1));
             }
             if file != 7 {
-                black_pawn_attack_board |= 1 << row_col_to_square(rank + 1, file + 1);
+                black_pawn_attack_board |= square_bitboard(row_col_to_square(rank + 1, file + 1));
             }
         }
         
         for offset in KNIGHT_OFFSETS {
             let test_square = square.wrapping_add_signed(offset);
             if square_is_on_board(test_square) {
-                knight_move_board |= 1 << test_square;
+                knight_move_board |= square_bitboard(test_square);
             }
         }
 
         for offset in KING_OFFSETS {
             let test_square = square.wrapping_add_signed(offset);
             if square_is_on_board(test_square) {
-                king_move_board |= 1 << test_square;
+                king_move_board |= square_bitboard(test_square);
             }
         }
+
+        let idx = square_to_index(square);
         
-        unsafe {
-            WHITE_PAWN_ATTACK_BOARDS[square as usize] = white_pawn_attack_board;
-            BLACK_PAWN_ATTACK_BOARDS[square as usize] = black_pawn_attack_board;
-            KNIGHT_MOVE_BOARDS[square as usize] = knight_move_board;
-            KING_MOVE_BOARDS[square as usize] = king_move_board;
+        move_boards.white_pawn_attack_boards[idx] = white_pawn_attack_board;
+        move_boards.black_pawn_attack_boards[idx] = black_pawn_attack_board;
+        move_boards.knight_move_boards[idx] = knight_move_board;
+        move_boards.king_move_boards[idx] = king_move_board;
+
+        for offset in KING_OFFSETS {
+
+            let mut between_board = 0;
+            let mut test_square = square.wrapping_add_signed(offset);
+
+            while square_is_on_board(test_square) {
+                move_boards.between_boards[idx][square_to_index(test_square)] = between_board;
+                between_board |= square_bitboard(test_square);
+                test_square = test_square.wrapping_add_signed(offset);
+            }
         }
     }
+
+    let mut offset = 0;
+
+    for square in VALID_SQUARES {
+        let magic = init_magic(&mut move_boards.sliding_attack_boards, square, ROOK_MAGICS[square_to_index(square)], &ORTHOGONAL_OFFSETS, offset);
+        offset += 1 << (64 - magic.shift);
+        move_boards.rook_magic_entries[square_to_index(square)] = magic;
+    }
+
+    for square in VALID_SQUARES {
+        let magic = init_magic(&mut move_boards.sliding_attack_boards, square, BISHOP_MAGICS[square_to_index(square)], &DIAGONAL_OFFSETS, offset);
+        offset += 1 << (64 - magic.shift);
+        move_boards.bishop_magic_entries[square_to_index(square)] = magic;
+    }
+
+    move_boards
+
 }
 
-pub fn get_pawn_attack_board(square: Square, colour: Colour) -> u128 {
-    unsafe {
-        match colour {
-            Colour::White => WHITE_PAWN_ATTACK_BOARDS[square as usize],
-            Colour::Black => BLACK_PAWN_ATTACK_BOARDS[square as usize]
+fn slow_sliding_attack_board(square: Square, occupied: Bitboard, offsets: &[i8; 4]) -> Bitboard {
+
+    let mut attack_board = 0;
+
+    for &offset in offsets {
+
+        let mut test_square = square.wrapping_add_signed(offset);
+
+        while square_is_on_board(test_square) {
+
+            attack_board |= square_bitboard(test_square);
+
+            if occupied & square_bitboard(test_square) != 0 {
+                break;
+            }
+
+            test_square = test_square.wrapping_add_signed(offset);
+
         }
     }
+
+    attack_board
+
 }
 
-pub fn get_knight_move_board(square: Square) -> u128 {
-    unsafe {
-        KNIGHT_MOVE_BOARDS[square as usize]
+fn init_magic(sliding_attack_boards: &mut [Bitboard], square: Square, magic: u64, offsets: &[i8; 4], offset: usize) -> Magic {
+
+    // edges never change the attack set so they stay out of the mask
+    let mut mask = 0;
+
+    for &dir in offsets {
+
+        let mut test_square = square.wrapping_add_signed(dir);
+
+        while square_is_on_board(test_square.wrapping_add_signed(dir)) {
+            mask |= square_bitboard(test_square);
+            test_square = test_square.wrapping_add_signed(dir);
+        }
     }
+
+    let magic = Magic {
+        mask,
+        magic,
+        shift: 64 - mask.count_ones(),
+        offset
+    };
+
+    let mut occupied: Bitboard = 0;
+
+    loop {
+
+        let attack_board = slow_sliding_attack_board(square, occupied, offsets);
+        
+        sliding_attack_boards[magic_index(magic, occupied)] = attack_board;
+
+        occupied = occupied.wrapping_sub(mask) & mask;
+
+        if occupied == 0 {
+            break;
+        }
+    }
+
+    magic
+
 }
 
-pub fn get_king_move_board(square: Square) -> u128 {
-    unsafe {
-        KING_MOVE_BOARDS[square as usize]
+#[inline(always)]
+fn magic_index(magic: Magic, occupied: Bitboard) -> usize {
+    magic.offset + ((occupied & magic.mask).wrapping_mul(magic.magic) >> magic.shift) as usize
+}
+
+pub fn get_pawn_attack_board(square: Square, colour: Colour) -> Bitboard {
+    match colour {
+        Colour::White => MOVE_BOARDS.white_pawn_attack_boards[square_to_index(square)],
+        Colour::Black => MOVE_BOARDS.black_pawn_attack_boards[square_to_index(square)]
     }
 }
 
+pub fn get_knight_move_board(square: Square) -> Bitboard {
+    MOVE_BOARDS.knight_move_boards[square_to_index(square)]
+}
+
+pub fn get_king_move_board(square: Square) -> Bitboard {
+    MOVE_BOARDS.king_move_boards[square_to_index(square)]
+}
+
+pub fn get_rook_attack_board(square: Square, occupied: Bitboard) -> Bitboard {
+    MOVE_BOARDS.sliding_attack_boards[magic_index(MOVE_BOARDS.rook_magic_entries[square_to_index(square)], occupied)]
+}
+
+pub fn get_bishop_attack_board(square: Square, occupied: Bitboard) -> Bitboard {
+    MOVE_BOARDS.sliding_attack_boards[magic_index(MOVE_BOARDS.bishop_magic_entries[square_to_index(square)], occupied)]
+}
+
+pub fn get_queen_attack_board(square: Square, occupied: Bitboard) -> Bitboard {
+    get_rook_attack_board(square, occupied) | get_bishop_attack_board(square, occupied)
+}
+
+// squares strictly between two squares on a shared line, empty if they don't share one
+pub fn get_between_board(square1: Square, square2: Square) -> Bitboard {
+    MOVE_BOARDS.between_boards[square_to_index(square1)][square_to_index(square2)]
+}
+
+pub fn square_to_index(square: Square) -> usize {
+    ((square + (square & 7)) >> 1) as usize
+}
+
+pub fn index_to_square(index: usize) -> Square {
+    (index + (index & !7)) as Square
+}
+
+pub fn square_bitboard(square: Square) -> Bitboard {
+    1 << square_to_index(square)
+}
+
+pub fn bitboard_squares(bitboard: Bitboard) -> BitboardSquares {
+    BitboardSquares(bitboard)
+}
+
+pub struct BitboardSquares(Bitboard);
+
+impl Iterator for BitboardSquares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+
+        if self.0 == 0 {
+            return None;
+        }
+
+        let idx = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+
+        Some(index_to_square(idx))
+
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitboardSquares {}
+
 pub fn square_to_rank_file(square: Square) -> (usize, usize) {
     (square as usize / 16, square as usize % 16)
 }
@@ -218,10 +499,10 @@ pub fn square_to_an(square: Square) -> String {
     )
 }
 
-pub fn print_bitboard(board: u128) {
+pub fn print_bitboard(board: Bitboard) {
     for rank in 0..8 {
         for file in 0..8 {
-            print!("{}", (board >> (file + rank * 16)) & 1)
+            print!("{}", (board >> (file + rank * 8)) & 1)
         }
         println!()
     }
@@ -1,13 +1,33 @@
 mod board;
+mod builder;
 mod movegen;
 mod piece;
 mod chess_util;
 mod r#move;
+mod movelist;
+mod notation;
 mod pgn;
+mod packed;
+mod perft;
+mod render;
+mod san;
+mod see;
+mod validate;
+mod variant;
 
 pub use board::*;
+pub use builder::*;
 pub use movegen::*;
 pub use piece::*;
 pub use chess_util::*;
 pub use r#move::*;
-pub use pgn::*;
\ No newline at end of file
+pub use movelist::*;
+pub use notation::*;
+pub use pgn::*;
+pub use packed::*;
+pub use perft::*;
+pub use render::*;
+pub use san::*;
+pub use see::*;
+pub use validate::*;
+pub use variant::*;
\ No newline at end of file
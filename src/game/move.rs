@@ -1,5 +1,8 @@
 use super::chess_util::*;
-use super::get_possible_moves;
+use super::san::{get_san_body, parse_square, SanError};
+use super::movegen::get_possible_moves;
+use super::see::see;
+use super::variant::*;
 use super::piece::*;
 use super::board::*;
 
@@ -9,7 +12,73 @@ pub enum MoveType {
     PawnDouble,
     Promotion(Piece),
     EnPassant,
-    Castle
+    Castle,
+    Null,
+    Drop(Piece)
+}
+
+// what the board was before a move lives in its undo stack, not here
+const NORMAL_FLAG: u16 = 0;
+const PAWN_DOUBLE_FLAG: u16 = 1;
+const EN_PASSANT_FLAG: u16 = 2;
+const CASTLE_FLAG: u16 = 3;
+// knight to queen
+const PROMOTION_FLAG: u16 = 4;
+// pawn to queen
+const DROP_FLAG: u16 = 8;
+const NULL_FLAG: u16 = 13;
+
+// a move in two bytes for tables and books, the start square in the low six bits, then
+// the end square and four bits of move type. the pieces and their colour come from the board,
+// so it only means something for the position it was packed in
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+
+    pub fn start_square(self) -> Square {
+        index_to_square(self.0 as usize & 0b111111)
+    }
+
+    pub fn end_square(self) -> Square {
+        index_to_square((self.0 as usize >> 6) & 0b111111)
+    }
+
+    fn flag(self) -> u16 {
+        self.0 >> 12
+    }
+
+    pub fn is_null(self) -> bool {
+        self.flag() == NULL_FLAG
+    }
+
+    // a packed move from some other position, like a hash collision, comes out as
+    // nonsense rather than failing, so check it against the legal moves before playing it
+    pub fn unpack(self, board: &Board) -> Move {
+
+        let start_square = self.start_square();
+        let end_square = self.end_square();
+        let colour = board.side_to_move as u8;
+
+        match self.flag() {
+            PAWN_DOUBLE_FLAG => Move::new_pawn_double(board, start_square, end_square),
+            EN_PASSANT_FLAG => Move::new_en_passant(board, start_square, end_square),
+            CASTLE_FLAG => Move::new_castle(board, start_square, end_square),
+            NULL_FLAG => Move::new_null(board),
+            flag @ PROMOTION_FLAG..DROP_FLAG => {
+                let promote_piece = Piece::from(colour | ((flag - PROMOTION_FLAG) as u8 + KNIGHT));
+                Move::new_promotion(board, start_square, end_square, promote_piece)
+            }
+            flag @ DROP_FLAG..NULL_FLAG => Move::new_drop(board, Piece::from(colour | (flag - DROP_FLAG) as u8), end_square),
+            _ => Move::new(board, start_square, end_square)
+        }
+    }
+}
+
+impl From<&Move> for PackedMove {
+    fn from(full_move: &Move) -> Self {
+        full_move.pack()
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -18,10 +87,32 @@ pub struct Move {
     pub end_square: Square,
     pub moved_piece: Piece,
     pub replaced_piece: Piece,
-    pub old_castling_rights : CastlingRights,
     pub move_type: MoveType
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveParseError {
+    BadLength(usize),
+    BadSquare,
+    BadPiece(char),
+    BadPromotion(char),
+    BadSan(SanError),
+    IllegalMove
+}
+
+impl std::fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::BadLength(length) => write!(f, "a move can't be {} characters long", length),
+            Self::BadSquare => write!(f, "bad square in move"),
+            Self::BadPiece(c) => write!(f, "'{}' can't be dropped", c),
+            Self::BadPromotion(c) => write!(f, "can't promote to '{}'", c),
+            Self::BadSan(err) => write!(f, "{}", err),
+            Self::IllegalMove => write!(f, "move is not legal here")
+        }
+    }
+}
+
 impl Default for Move {
     fn default() -> Self {
         Self {
@@ -29,7 +120,6 @@ impl Default for Move {
             end_square: 0,
             moved_piece: Empty,
             replaced_piece: Empty,
-            old_castling_rights: 0,
             move_type: MoveType::Normal
         }
     }
@@ -47,7 +137,6 @@ impl Move {
             end_square,
             replaced_piece,
             moved_piece,
-            old_castling_rights: board.castling_rights,
             move_type
         }
     }
@@ -68,12 +157,117 @@ impl Move {
         Self::create_move(board, start_square, end_square, MoveType::Promotion(promote_piece))
     }
 
-    pub fn new_castle(board: &Board, start_square: Square, end_square: Square) -> Self {
-        Move::create_move(board, start_square, end_square, MoveType::Castle)
+    // end_square is the rook being castled with, so chess960 castles are unambiguous
+    pub fn new_castle(board: &Board, king_square: Square, rook_square: Square) -> Self {
+        Self {
+            replaced_piece: Empty,
+            ..Move::create_move(board, king_square, rook_square, MoveType::Castle)
+        }
+    }
+
+    // passes the turn, only for the search to play with
+    pub fn new_null(board: &Board) -> Self {
+        Self {
+            start_square: 0,
+            end_square: 0,
+            moved_piece: Empty,
+            replaced_piece: Empty,
+            ..Move::create_move(board, 0, 0, MoveType::Null)
+        }
+    }
+
+    // crazyhouse, a piece from the pocket put onto an empty square
+    pub fn new_drop(board: &Board, piece: Piece, square: Square) -> Self {
+        Self {
+            moved_piece: piece,
+            ..Move::create_move(board, square, square, MoveType::Drop(piece))
+        }
     }
 
+    pub fn is_capture(&self) -> bool {
+        self.replaced_piece != Empty || self.move_type == MoveType::EnPassant
+    }
+
+    // the pawn taken en passant isn't on the end square, so it's never the replaced piece
+    pub fn captured_piece(&self) -> Piece {
+        match self.move_type {
+            MoveType::EnPassant => Piece::from(self.moved_piece.get_colour().opposite() as u8 | PAWN),
+            _ => self.replaced_piece
+        }
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        matches!(self.move_type, MoveType::Promotion(_))
+    }
+
+    pub fn promotion_piece(&self) -> Option<Piece> {
+        match self.move_type {
+            MoveType::Promotion(promote_piece) => Some(promote_piece),
+            _ => None
+        }
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.move_type == MoveType::Castle
+    }
+
+    pub fn is_drop(&self) -> bool {
+        matches!(self.move_type, MoveType::Drop(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.move_type == MoveType::Null
+    }
+
+    // neither takes anything nor promotes
+    pub fn is_quiet(&self) -> bool {
+        !self.is_capture() && !self.is_promotion()
+    }
+
+    // the rest need the board the move is played from
+    pub fn gives_check(&self, board: &Board) -> bool {
+        board.gives_check(self)
+    }
+
+    // no position from before the move can come up again after it
+    pub fn is_irreversible(&self, board: &Board) -> bool {
+
+        if self.is_capture() || self.moved_piece.is_pawn() || self.is_castle() || self.is_drop() {
+            return true;
+        }
+
+        if self.is_null() {
+            return false;
+        }
+
+        // the remaining checks are part of the position
+        if board.variant == Variant::ThreeCheck && board.gives_check(self) {
+            return true;
+        }
+
+        let colour = self.moved_piece.get_colour();
+        let king_rights = get_castling_right(colour, true) | get_castling_right(colour, false);
+
+        CASTLING_RIGHTS.iter().any(|&castling_right| board.castling_rights & castling_right != 0 && (
+            (self.moved_piece.is_king() && king_rights & castling_right != 0)
+            || [self.start_square, self.end_square].contains(&board.get_castling_rook(castling_right))
+        ))
+    }
+
+    pub fn see(&self, board: &Board) -> i32 {
+        see(board, self)
+    }
+
+    // trusts that the move is legal, board.parse_uci_move checks
     pub fn from_long_an(long_an: &str, board: &Board) -> Self {
 
+        // drops look like P@e4
+        if long_an.chars().nth(1) == Some('@') {
+            let piece_char = long_an.chars().next().unwrap().to_ascii_uppercase();
+            let piece = Piece::from(Piece::from_char(piece_char) as u8 | board.side_to_move as u8);
+            return Self::new_drop(board, piece, an_to_square(long_an[2..].trim().to_string()));
+        }
+
         let start_square = an_to_square(String::from(long_an));
         let end_square = an_to_square(long_an.to_string()[2..].trim().to_string());
         let diff = start_square.max(end_square) - start_square.min(end_square);
@@ -86,158 +280,77 @@ impl Move {
             else if diff != 16 && board.get_piece(end_square) == Piece::Empty {
                 Self::new_en_passant(board, start_square, end_square)
             }
-            else if !(16..=112).contains(&end_square) { // weirdest linting suggestion i've ever seen
-                Self::new_promotion(board, start_square, end_square, Piece::from_char(long_an.to_string().chars().collect::<Vec<char>>()[4]))
+            else if end_square >> 4 == 0 || end_square >> 4 == 7 {
+                // the promotion is written in lowercase whoever is moving
+                let promote_code = long_an.chars().nth(4).and_then(|c| Piece::try_from_char(c.to_ascii_uppercase())).map_or(QUEEN, |piece| piece as u8);
+                Self::new_promotion(board, start_square, end_square, Piece::from(promote_code | piece.get_colour() as u8))
             }
             else {
                 Self::new(board, start_square, end_square) // necessary duplicate to cover all cases
             }
         }
-        else if piece.is_king() && diff == 2 {
+        else if piece.is_king() && board.get_piece(end_square) == (piece.get_colour() as u8 | ROOK).into() {
             Self::new_castle(board, start_square, end_square)
         }
+        else if piece.is_king() && diff == 2 && !board.chess960 {
+            let castling_right = get_castling_right(piece.get_colour(), end_square > start_square);
+            Self::new_castle(board, start_square, board.get_castling_rook(castling_right))
+        }
         else {
             Self::new(board, start_square, end_square)
         }
     }
 
     pub fn from_an(an: &str, board: &Board) -> Option<Self> {
+        board.parse_san(an).ok()
+    }
 
-        let mut chars: Vec<_> = an.chars().collect();
-
-        match chars.last() {
-            None => return None,
-            Some('+' | '#') => {
-                chars.pop();
-            }
-            _ => {}
-        }
-
-        if chars == "O-O-O".chars().collect::<Vec<_>>() {
-            if board.side_to_move == Colour::White {
-                return Some(Self::new_castle(board, E1, C1));
-            }
-            else {
-                return Some(Self::new_castle(board, E8, C8));
-            }
-        }
-        else if chars == "O-O".chars().collect::<Vec<_>>() {
-            if board.side_to_move == Colour::White {
-                return Some(Self::new_castle(board, E1, G1));
-            }
-            else {
-                return Some(Self::new_castle(board, E8, G8));
-            }
-        }
+    pub fn pack(&self) -> PackedMove {
 
-        let piece_code = if chars[0].is_uppercase() {
-            match chars[0] {
-                'N' => KNIGHT,
-                'B' => BISHOP,
-                'R' => ROOK,
-                'Q' => QUEEN,
-                'K' => KING,
-                _ => return None
-            }
-        }
-        else {
-            PAWN
+        let flag = match self.move_type {
+            MoveType::Normal => NORMAL_FLAG,
+            MoveType::PawnDouble => PAWN_DOUBLE_FLAG,
+            MoveType::EnPassant => EN_PASSANT_FLAG,
+            MoveType::Castle => CASTLE_FLAG,
+            MoveType::Null => NULL_FLAG,
+            MoveType::Promotion(piece) => PROMOTION_FLAG + (piece as u16 & 0b111) - KNIGHT as u16,
+            MoveType::Drop(piece) => DROP_FLAG + (piece as u16 & 0b111)
         };
 
-        if piece_code != PAWN {
-            chars.remove(0);
-        }
-
-        let moved_piece = Piece::from(piece_code | board.side_to_move as u8);
+        PackedMove(
+            square_to_index(self.start_square) as u16
+            | (square_to_index(self.end_square) as u16) << 6
+            | flag << 12
+        )
+    }
 
-        let promotion_piece = if chars.contains(&'=') {
-            
-            let promotion_code = match chars.pop().unwrap() {
-                'N' => KNIGHT,
-                'B' => BISHOP,
-                'R' => ROOK,
-                'Q' => QUEEN,
-                _ => return None
-            };
-            
-            chars.pop();
+    pub fn as_long_an(&self) -> String {
+        self.as_uci(false)
+    }
 
-            Some(Piece::from(promotion_code | board.side_to_move as u8))
+    // chess960 castles are written as the king taking its rook, standard ones as the king's two step
+    pub fn as_uci(&self, chess960: bool) -> String {
 
+        if self.move_type == MoveType::Null {
+            return String::from("0000");
         }
-        else {
-            None
-        };
-
-        let mut end_square_string = String::new();
-        end_square_string.push(chars.pop().unwrap());
-        end_square_string.insert(0, chars.pop().unwrap());
-
-        let end_square = an_to_square(end_square_string);
 
-        let takes = chars.contains(&'x');
-        if takes {
-            chars.pop();
+        if let MoveType::Drop(piece) = self.move_type {
+            return format!("{}@{}", piece.to_an_char(), square_to_an(self.end_square));
         }
 
-        let (disambiguate_file, disambiguate_rank) = match chars.len() {
-            2 => (
-                (Some((chars.pop().unwrap() as u8) - ('a' as u8))),
-                (Some((chars.pop().unwrap() as u8) - ('0' as u8)))
-            ),
-            1 => {
-
-                let char = chars.pop().unwrap();
-
-                if char.is_numeric() {
-                    (None, Some((char as u8) - ('0' as u8)))
-                }
-                else {
-                    (Some((char as u8) - ('a' as u8)), None)
-                }
-            }
-            _ => (None, None)
-        };
-
-        let legal_moves = get_possible_moves(board);
-
-        for legal_move in legal_moves {
-
-            if legal_move.end_square != end_square || legal_move.moved_piece != moved_piece {
-                continue;
-            }
-
-            if let MoveType::Promotion(legal_promote_piece) = legal_move.move_type {
-                if Some(legal_promote_piece) != promotion_piece {
-                    continue;
-                }
-            }
-
-            if let Some(rank) = disambiguate_rank {
-                if rank != (legal_move.start_square) / 16 {
-                    continue;
-                }
-            }
-
-            if let Some(file) = disambiguate_file {
-                if file != legal_move.start_square % 8 {
-                    continue;
-                }
-            }
-
-            return Some(legal_move)
-
+        let end_square = if self.move_type == MoveType::Castle && !chess960 {
+            let castling_right = get_castling_right(self.moved_piece.get_colour(), self.end_square > self.start_square);
+            get_castling_end_squares(castling_right).0
         }
+        else {
+            self.end_square
+        };
 
-        None
-
-    }
-
-    pub fn as_long_an(&self) -> String {
         format!(
             "{}{}{}",
             square_to_an(self.start_square),
-            square_to_an(self.end_square),
+            square_to_an(end_square),
             if let MoveType::Promotion(piece) = self.move_type {
                 piece.to_char().to_lowercase().to_string()
             }
@@ -247,78 +360,80 @@ impl Move {
         )
     }
 
+    // san without the check suffix, board.san adds it
     pub fn as_an(&self, possible_moves: &[Move]) -> String {
+        get_san_body(self, possible_moves)
+    }
+}
 
-        let moved_piece = self.moved_piece;
+impl Board {
 
-        let mut distinguish_file = false;
-        let mut distinguish_rank = false;
+    // only takes a move if it's legal here, castles are the king taking its rook in chess960
+    // and either that or the king's two step otherwise
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, MoveParseError> {
 
-        for possible_move in possible_moves {
-            if possible_move.moved_piece == moved_piece && possible_move.end_square == self.end_square && possible_move.start_square != self.start_square {
-                
-                if possible_move.start_square % 8 == self.start_square % 8 {
-                    distinguish_rank = true;
-                }
-                else if possible_move.start_square / 8 == self.start_square / 8 {
-                    distinguish_file = true;
-                }
-            }
+        let chars: Vec<char> = uci.trim().chars().collect();
+
+        // passing while in check would leave the king attacked
+        if chars == ['0', '0', '0', '0'] {
+            return if self.is_in_check() {Err(MoveParseError::IllegalMove)} else {Ok(Move::new_null(self))};
         }
 
-        if self.move_type == MoveType::Castle {
-            return String::from(match self.end_square {
-                C1 | C8 => "O-O-O",
-                G1 | G8 => "O-O",
-                _ => unreachable!()
-            })
+        let possible_moves = get_possible_moves(self);
+
+        // drops look like N@e4
+        if let [piece_char, '@', file, rank] = chars[..] {
+
+            let drop_piece = match Piece::try_from_char(piece_char.to_ascii_uppercase()) {
+                Some(piece) if !piece.is_king() => Piece::from(piece as u8 | self.side_to_move as u8),
+                _ => return Err(MoveParseError::BadPiece(piece_char))
+            };
+
+            let square = parse_square(file, rank).ok_or(MoveParseError::BadSquare)?;
+
+            return possible_moves.into_iter().find(|legal_move|
+                legal_move.move_type == MoveType::Drop(drop_piece) && legal_move.end_square == square
+            ).ok_or(MoveParseError::IllegalMove);
         }
 
-        let mut piece_name = if moved_piece.is_pawn() {
-            String::new()
+        if chars.len() != 4 && chars.len() != 5 {
+            return Err(MoveParseError::BadLength(chars.len()));
         }
-        else {
-            moved_piece.to_an_char().to_string()
-        };
 
-        let start_square_an = square_to_an(self.start_square);
+        let start_square = parse_square(chars[0], chars[1]).ok_or(MoveParseError::BadSquare)?;
+        let end_square = parse_square(chars[2], chars[3]).ok_or(MoveParseError::BadSquare)?;
 
-        let takes = if self.replaced_piece != Empty || self.move_type == MoveType::EnPassant {
-            if moved_piece.is_pawn() {
-                piece_name = start_square_an.chars().nth(0).unwrap().to_string();
+        let promote_code = match chars.get(4) {
+            None => None,
+            Some(&c) => match Piece::try_from_char(c.to_ascii_uppercase()) {
+                Some(piece) if !piece.is_pawn() && !piece.is_king() => Some(piece as u8),
+                _ => return Err(MoveParseError::BadPromotion(c))
             }
-            "x"
-        }
-        else {
-            ""
         };
 
-        let start_square = if distinguish_rank && distinguish_file {
-            start_square_an
-        }
-        else if distinguish_rank {
-            start_square_an.chars().nth(1).unwrap().to_string()
-        }
-        else if distinguish_file {
-            start_square_an.chars().nth(0).unwrap().to_string()
-        }
-        else {
-            String::new()
-        };
+        possible_moves.into_iter().find(|legal_move| {
 
-        let promotion = if let MoveType::Promotion(promote_piece) = self.move_type {
-            format!("={}", promote_piece.to_an_char())
-        }
-        else {
-            String::new()
-        };
+            if legal_move.start_square != start_square {
+                return false;
+            }
+
+            let matches_end_square = match legal_move.move_type {
+                MoveType::Castle if !self.chess960 => {
+                    let castling_right = get_castling_right(self.side_to_move, legal_move.end_square > legal_move.start_square);
+                    end_square == get_castling_end_squares(castling_right).0 || end_square == legal_move.end_square
+                }
+                MoveType::Drop(_) | MoveType::Null => false,
+                _ => end_square == legal_move.end_square
+            };
+
+            let matches_promotion = match legal_move.move_type {
+                MoveType::Promotion(promote_piece) => promote_code == Some(promote_piece as u8 & 0b111),
+                _ => promote_code.is_none()
+            };
+
+            matches_end_square && matches_promotion
+
+        }).ok_or(MoveParseError::IllegalMove)
 
-        return format!("{}{}{}{}{}",
-            piece_name,
-            start_square,
-            takes,
-            square_to_an(self.end_square),
-            promotion
-        );
     }
 }
@@ -3,6 +3,8 @@ use super::chess_util::*;
 use super::piece::*;
 use super::board::*;
 use super::r#move::*;
+use super::variant::*;
+use super::movelist::*;
 
 pub fn is_back_rank(colour: Colour, square: Square) -> bool {
     (colour == White && square >= A1) || (colour == Black && square <= H8)
@@ -12,32 +14,35 @@ pub fn is_back_two_ranks(colour: Colour, square: Square) -> bool {
     (colour == White && square >= A2) || (colour == Black && square <= H7)
 }
 
-fn try_add_move(moves: &mut Vec<Move>, board: &Board, start_square: Square, offset: i8) -> bool {
+// which part of the legal moves to generate, so a search can stop before the quiet ones
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GenType {
+    All,
+    Captures,
+    Quiets
+}
 
-    let end_square = start_square.wrapping_add_signed(offset);
+// captures and promotions, what get_capture_moves gives
+pub fn is_noisy(possible_move: &Move) -> bool {
+    !possible_move.is_quiet()
+}
 
-    if !square_is_on_board(end_square) {
-        return false;
+// the squares pieces other than pawns can move to
+fn get_target_squares(board: &Board, colour: Colour, gen_type: GenType) -> Bitboard {
+    match gen_type {
+        GenType::All => !board.get_colour_bitboard(colour),
+        GenType::Captures => board.get_colour_bitboard(colour.opposite()),
+        GenType::Quiets => !board.get_occupied_bitboard()
     }
-    
-    let end_piece = board.get_piece(end_square);
-
-    let end_piece_empty = end_piece == Empty;
-    let same_colour = board.get_piece(start_square).same_colour(end_piece);
+}
 
-    if !end_piece_empty && same_colour {
-        return false;
+fn add_moves_from_bitboard(moves: &mut MoveList, board: &Board, start_square: Square, end_squares: Bitboard) {
+    for end_square in bitboard_squares(end_squares) {
+        moves.push(Move::new(board, start_square, end_square));
     }
-
-    let new_move = Move::new(board, start_square, end_square);
-    let is_empty = new_move.replaced_piece == Empty;
-    moves.push(new_move);
-
-    is_empty
-
 }
 
-fn add_pawn_moves(moves: &mut Vec<Move>, board:&Board, start_square: Square, end_square: Square, colour: u8, is_promo: bool) {
+fn add_pawn_moves(moves: &mut MoveList, board:&Board, start_square: Square, end_square: Square, colour: u8, is_promo: bool) {
     if is_promo {
         moves.push(Move::new_promotion(board, start_square, end_square, (colour | QUEEN).into()));
         moves.push(Move::new_promotion(board, start_square, end_square, (colour | ROOK).into()));
@@ -49,12 +54,34 @@ fn add_pawn_moves(moves: &mut Vec<Move>, board:&Board, start_square: Square, end
     }
 }
 
-fn gen_valid_pawn_moves(moves: &mut Vec<Move>, board: &Board, start_square: Square, colour: Colour) {
+fn gen_valid_pawn_moves(moves: &mut MoveList, board: &Board, start_square: Square, colour: Colour, gen_type: GenType) {
 
     let forward_square = colour.offset_rank(start_square);
+    let is_promo = is_back_rank(colour.opposite(), forward_square);
+
+    // promotions count as captures, even the ones that don't take anything
+    let pushes_wanted = if is_promo {gen_type != GenType::Quiets} else {gen_type != GenType::Captures};
+
+    if gen_type != GenType::Quiets {
+        add_pawn_captures(moves, board, start_square, colour, is_promo);
+    }
+
+    if pushes_wanted && board.get_piece(forward_square) == Piece::Empty {
+
+        add_pawn_moves(moves, board, start_square, forward_square, colour as u8, is_promo);
+
+        let double_move_square = colour.offset_rank(forward_square);
+
+        if is_back_two_ranks(colour, start_square) && board.get_piece(double_move_square) == Empty {
+            moves.push(Move::new_pawn_double(board, start_square, double_move_square));
+        }
+    }
+}
+
+fn add_pawn_captures(moves: &mut MoveList, board: &Board, start_square: Square, colour: Colour, is_promo: bool) {
 
+    let forward_square = colour.offset_rank(start_square);
     let file = start_square % 16;
-    let is_promo = is_back_rank(colour.opposite(), forward_square);
 
     if file != 0 {
 
@@ -76,17 +103,6 @@ fn gen_valid_pawn_moves(moves: &mut Vec<Move>, board: &Board, start_square: Squa
         }
     }
 
-    if board.get_piece(forward_square) == Piece::Empty {
-
-        add_pawn_moves(moves, board, start_square, forward_square, colour as u8, is_promo);
-
-        let double_move_square = colour.offset_rank(forward_square);
-
-        if is_back_two_ranks(colour, start_square) && board.get_piece(double_move_square) == Empty {
-            moves.push(Move::new_pawn_double(board, start_square, double_move_square));
-        }
-    }
-
     if let Some(en_passant_square) = board.en_passant_chance {
         if (en_passant_square as i8 - colour.offset_rank(start_square) as i8).abs() == 1 {
             moves.push(Move::new_en_passant(board, start_square, en_passant_square))
@@ -95,219 +111,772 @@ fn gen_valid_pawn_moves(moves: &mut Vec<Move>, board: &Board, start_square: Squa
 
 }
 
-fn add_bishop_moves(moves: &mut Vec<Move>, board: &Board, start_square: Square) {
-    
-    for offset in DIAGONAL_OFFSETS {
+fn add_bishop_moves(moves: &mut MoveList, board: &Board, start_square: Square, target_squares: Bitboard) {
+    let attack_board = get_bishop_attack_board(start_square, board.get_occupied_bitboard());
+    add_moves_from_bitboard(moves, board, start_square, attack_board & target_squares);
+}
+
+fn add_rook_moves(moves: &mut MoveList, board: &Board, start_square: Square, target_squares: Bitboard) {
+    let attack_board = get_rook_attack_board(start_square, board.get_occupied_bitboard());
+    add_moves_from_bitboard(moves, board, start_square, attack_board & target_squares);
+}
+
+// works for chess960 too, the king and rook just have to end up on the usual squares
+fn add_castling_moves(moves: &mut MoveList, board: &Board, colour: Colour, opponent_attacked_squares: Bitboard) {
+
+    let king_square = board.get_king_square(colour);
+    let own_rook = (colour as u8 | ROOK).into();
+
+    let opp_colour = colour.opposite() as u8;
+    let opp_orthogonal_pieces = board.get_piece_bitboard((ROOK | opp_colour).into()) | board.get_piece_bitboard((QUEEN | opp_colour).into());
+
+    for kingside in [true, false] {
 
-        let mut total_offset = offset;
+        let castling_right = get_castling_right(colour, kingside);
 
-        while try_add_move(moves, board, start_square, total_offset) {
-            total_offset = total_offset.wrapping_add(offset);
+        if board.castling_rights & castling_right == NO_CASTLING_RIGHTS {
+            continue;
         }
-    }
-}
 
-fn add_rook_moves(moves: &mut Vec<Move>, board: &Board, start_square: Square) {
-    
-    for offset in ORTHOGONAL_OFFSETS {
+        let rook_square = board.get_castling_rook(castling_right);
+        let (king_end_square, rook_end_square) = get_castling_end_squares(castling_right);
+
+        if board.get_piece(rook_square) != own_rook || king_square / 16 != king_end_square / 16 {
+            continue;
+        }
+
+        let king_path = get_between_board(king_square, king_end_square) | square_bitboard(king_end_square);
+        let rook_path = get_between_board(rook_square, rook_end_square) | square_bitboard(rook_end_square);
 
-        let mut total_offset = offset;
+        // the king and rook can pass through each other
+        let occupied = board.get_occupied_bitboard() & !square_bitboard(king_square) & !square_bitboard(rook_square);
 
-        while try_add_move(moves, board, start_square, total_offset) {
-            total_offset = total_offset.wrapping_add(offset);
+        if (king_path | rook_path) & occupied != 0 || (king_path | square_bitboard(king_square)) & opponent_attacked_squares != 0 {
+            continue;
         }
+
+        // the rook might have been shielding the king's destination along the back rank
+        if get_rook_attack_board(king_end_square, occupied | square_bitboard(rook_end_square)) & opp_orthogonal_pieces != 0 {
+            continue;
+        }
+
+        moves.push(Move::new_castle(board, king_square, rook_square));
+
     }
 }
 
-pub fn get_possible_moves(board: &Board) -> Vec<Move> {
+// every move but the king's, ignoring pins and checks
+fn add_piece_moves(moves: &mut MoveList, board: &Board, colour: Colour, gen_type: GenType) {
 
-    let side_to_move = board.side_to_move;
-    let mut moves: Vec<Move> = Vec::with_capacity(32);
-
-    let king_square: Square;
-    let pawn: Piece;
-    let knight: Piece;
-    let bishop: Piece;
-    let rook: Piece;
-    let queen: Piece;
-    let own_king: Piece;
-    
-    let position_info = get_position_info(board, side_to_move);
+    let target_squares = get_target_squares(board, colour, gen_type);
 
-    if side_to_move == White {
+    for pawn in board.get_piece_squares((colour as u8 | PAWN).into()) {
+        gen_valid_pawn_moves(moves, board, pawn, colour, gen_type);
+    }
 
-        pawn = WhitePawn;
-        knight = WhiteKnight;
-        bishop = WhiteBishop;
-        rook = WhiteRook;
-        queen = WhiteQueen;
+    for knight in board.get_piece_squares((colour as u8 | KNIGHT).into()) {
+        add_moves_from_bitboard(moves, board, knight, get_knight_move_board(knight) & target_squares);
+    }
 
-        king_square = board.white_king;
-        own_king = WhiteKing;
+    for bishop in board.get_piece_squares((colour as u8 | BISHOP).into()) {
+        add_bishop_moves(moves, board, bishop, target_squares);
+    }
 
-        if board.castling_rights & WHITE_KINGSIDE != NO_CASTLING_RIGHTS && board.get_piece(F1) == Empty && board.get_piece(G1) == Empty &&
-        position_info.opponent_attacked_squares & (1 << F1 | 1 << G1) == 0 {
-            moves.push(Move::new_castle(board, king_square, G1));
-        }
-        if board.castling_rights & WHITE_QUEENSIDE != NO_CASTLING_RIGHTS && board.get_piece(B1) == Empty && board.get_piece(C1) == Empty &&
-        board.get_piece(D1) == Empty && position_info.opponent_attacked_squares & (1 << C1 | 1 << D1) == 0 {
-            moves.push(Move::new_castle(board, king_square, C1));
-        }
+    for rook in board.get_piece_squares((colour as u8 | ROOK).into()) {
+        add_rook_moves(moves, board, rook, target_squares);
     }
-    else {
 
-        pawn = BlackPawn;
-        knight = BlackKnight;
-        bishop = BlackBishop;
-        rook = BlackRook;
-        queen = BlackQueen;
+    for queen in board.get_piece_squares((colour as u8 | QUEEN).into()) {
+        add_bishop_moves(moves, board, queen, target_squares);
+        add_rook_moves(moves, board, queen, target_squares);
+    }
+}
+
+// crazyhouse, anything in hand onto the empty target squares, pawns kept off the back ranks
+fn add_drop_moves(moves: &mut MoveList, board: &Board, colour: Colour, target_squares: Bitboard) {
+
+    let empty_squares = target_squares & !board.get_occupied_bitboard();
+
+    for piece_code in [PAWN, KNIGHT, BISHOP, ROOK, QUEEN] {
 
-        king_square = board.black_king;
-        own_king = BlackKing;
+        let piece = (colour as u8 | piece_code).into();
 
-        if board.castling_rights & BLACK_KINGSIDE != NO_CASTLING_RIGHTS && board.get_piece(F8) == Empty && board.get_piece(G8) == Empty &&
-        position_info.opponent_attacked_squares & (1 << F8 | 1 << G8) == 0 {
-            moves.push(Move::new_castle(board, king_square, G8));
+        if board.get_pocket_count(piece) == 0 {
+            continue;
         }
-        if board.castling_rights & BLACK_QUEENSIDE != NO_CASTLING_RIGHTS && board.get_piece(B8) == Empty && board.get_piece(C8) == Empty &&
-        board.get_piece(D8) == Empty && position_info.opponent_attacked_squares & (1 << C8 | 1 << D8) == 0 {
-            moves.push(Move::new_castle(board, king_square, C8));
+
+        let drop_squares = if piece_code == PAWN {empty_squares & !BACK_RANKS} else {empty_squares};
+
+        for square in bitboard_squares(drop_squares) {
+            moves.push(Move::new_drop(board, piece, square));
         }
     }
+}
 
-    for &pawn in &board.piece_positions[pawn as usize] {
-        gen_valid_pawn_moves(&mut moves, board, pawn, side_to_move);
-    }
+pub fn get_possible_moves(board: &Board) -> MoveList {
+    let mut moves = MoveList::new();
+    generate_moves(&mut moves, board, GenType::All);
+    moves
+}
 
-    for &knight in &board.piece_positions[knight as usize] {
-        for offset in KNIGHT_OFFSETS {
-            try_add_move(&mut moves, board, knight, offset);
-        }
+// legal captures and promotions, the first stage of a search
+pub fn get_capture_moves(board: &Board) -> MoveList {
+    let mut moves = MoveList::new();
+    generate_moves(&mut moves, board, GenType::Captures);
+    moves
+}
+
+// every legal move get_capture_moves leaves out
+pub fn get_quiet_moves(board: &Board) -> MoveList {
+    let mut moves = MoveList::new();
+    generate_moves(&mut moves, board, GenType::Quiets);
+    moves
+}
+
+// all the ways out of check, nothing when the side to move isn't in it
+pub fn get_evasion_moves(board: &Board) -> MoveList {
+
+    let mut moves = MoveList::new();
+
+    if !board.is_in_check() || board.get_variant_win().is_some() {
+        return moves;
     }
 
-    for &bishop in &board.piece_positions[bishop as usize] {
-        add_bishop_moves(&mut moves, board, bishop);
+    // an explosion next to either king can settle a check from anywhere on the board
+    if board.variant == Variant::Atomic {
+        add_atomic_moves(&mut moves, board);
     }
+    else {
+        add_evasion_moves(&mut moves, board);
+    }
+
+    moves
 
-    for &rook in &board.piece_positions[rook as usize] {
-        add_rook_moves(&mut moves, board, rook);
+}
+
+// quiet moves that check the opponent, for when captures alone aren't enough
+pub fn get_quiet_checks(board: &Board) -> MoveList {
+    let mut moves = get_quiet_moves(board);
+    moves.retain(|quiet_move| gives_check(board, quiet_move));
+    moves
+}
+
+// fills a list the caller owns, handing a whole list back up through every layer would copy it each time
+fn generate_moves(moves: &mut MoveList, board: &Board, gen_type: GenType) {
+
+    if board.variant != Variant::Standard {
+        return add_variant_moves(moves, board, gen_type);
     }
 
-    for &queen in &board.piece_positions[queen as usize] {
-        add_bishop_moves(&mut moves, board, queen);
-        add_rook_moves(&mut moves, board, queen);
+    add_standard_moves(moves, board, gen_type);
+
+}
+
+fn add_standard_moves(moves: &mut MoveList, board: &Board, gen_type: GenType) {
+
+    let side_to_move = board.side_to_move;
+
+    let king_square = board.get_king_square(side_to_move);
+    let own_king: Piece = (side_to_move as u8 | KING).into();
+    
+    let position_info = get_position_info(board, side_to_move);
+
+    if gen_type != GenType::Captures {
+        add_castling_moves(moves, board, side_to_move, position_info.opponent_attacked_squares);
     }
 
-    // just prevent walking king into an attack to start with
-    for offset in KING_OFFSETS {
-        let test_square = king_square.wrapping_add_signed(offset);
-        if test_square < 0x80 && position_info.opponent_attacked_squares & (1 << test_square) == 0 {
-            try_add_move(&mut moves, board, king_square, offset);
-        }
+    add_piece_moves(moves, board, side_to_move, gen_type);
+
+    // a drop can't uncover anything, so only blocking a check needs checking like any other move
+    if board.variant == Variant::Crazyhouse && gen_type != GenType::Captures {
+        add_drop_moves(moves, board, side_to_move, !0);
     }
 
-    let mut legal_moves: Vec<Move> = Vec::with_capacity(moves.capacity());
+    // just prevent walking king into an attack to start with
+    add_moves_from_bitboard(
+        moves,
+        board,
+        king_square,
+        get_king_move_board(king_square) & get_target_squares(board, side_to_move, gen_type) & !position_info.opponent_attacked_squares
+    );
 
+    // the illegal ones are dropped in place rather than copied into another list
     match position_info.king_attacker_count {
         0 => {
             
             // can't move king into check or move pinned pieces
-            for pseudo_legal_move in moves {
-    
-                let mut is_pinned = false;
-    
-                for (pinned_square, safe_squares) in &position_info.pinned_pieces {
-                    if *pinned_square == pseudo_legal_move.start_square && safe_squares & 1 << pseudo_legal_move.end_square == 0 {
-                        is_pinned = true;
-                        break;
-                    }
-                }
-    
-                if !is_pinned {
-                    legal_moves.push(pseudo_legal_move);
+            moves.retain(|pseudo_legal_move| {
+
+                if pseudo_legal_move.move_type == MoveType::EnPassant {
+                    return is_legal_en_passant(board, pseudo_legal_move);
                 }
-            }
+
+                position_info.pinned_pieces.get_safe_squares(pseudo_legal_move.start_square) & square_bitboard(pseudo_legal_move.end_square) != 0
+
+            });
         }
         1 if position_info.king_block_board != 0 => {
             
             // can only move king out of the way or block, no castling though
-            for pseudo_legal_move in moves {
+            moves.retain(|pseudo_legal_move| {
     
                 if pseudo_legal_move.move_type == MoveType::Castle {
-                    continue;
+                    return false;
                 }
-    
-                if pseudo_legal_move.moved_piece == own_king && pseudo_legal_move.move_type != MoveType::Castle ||
-                pseudo_legal_move.moved_piece != own_king && position_info.king_block_board & 1 << pseudo_legal_move.end_square != 0 {
-                    
-                    let mut is_pinned = false;
-    
-                    for (pinned_square, safe_squares) in &position_info.pinned_pieces {
-                        if *pinned_square == pseudo_legal_move.start_square && safe_squares & 1 << pseudo_legal_move.end_square == 0 {
-                            is_pinned = true;
-                            break;
-                        }
-                    }
-    
-                    if !is_pinned {
-                        legal_moves.push(pseudo_legal_move);
-                    }
+
+                // taking the checking pawn en passant doesn't land on it
+                if pseudo_legal_move.move_type == MoveType::EnPassant {
+                    return is_legal_en_passant(board, pseudo_legal_move);
                 }
-            }
+    
+                (pseudo_legal_move.moved_piece == own_king ||
+                position_info.king_block_board & square_bitboard(pseudo_legal_move.end_square) != 0) &&
+                position_info.pinned_pieces.get_safe_squares(pseudo_legal_move.start_square) & square_bitboard(pseudo_legal_move.end_square) != 0
+
+            });
         }
         _ => {
 
             // can only move king, no castling though
-            for pseudo_legal_move in moves {
+            moves.retain(|pseudo_legal_move|
+                pseudo_legal_move.moved_piece == own_king && pseudo_legal_move.move_type != MoveType::Castle
+            );
+        }
+    }
+}
+
+// the king steps out of the way, and against a single checker anything else can take it or block
+fn add_evasion_moves(moves: &mut MoveList, board: &Board) {
+
+    let side_to_move = board.side_to_move;
+    let king_square = board.get_king_square(side_to_move);
+    let position_info = get_position_info(board, side_to_move);
+    let checkers = get_checkers(board, side_to_move);
+
+    if checkers.count_ones() == 1 {
 
-                if pseudo_legal_move.moved_piece == own_king
-                && pseudo_legal_move.move_type != MoveType::Castle {
-                    legal_moves.push(pseudo_legal_move);
+        let checker_square = index_to_square(checkers.trailing_zeros() as usize);
+        let block_squares = get_between_board(king_square, checker_square);
+        let target_squares = block_squares | checkers;
+
+        for pawn in board.get_piece_squares((side_to_move as u8 | PAWN).into()) {
+
+            let attack_board = get_pawn_attack_board(pawn, side_to_move);
+            let forward_square = side_to_move.offset_rank(pawn);
+            let is_promo = is_back_rank(side_to_move.opposite(), forward_square);
+
+            if attack_board & checkers != 0 {
+                add_pawn_moves(moves, board, pawn, checker_square, side_to_move as u8, is_promo);
+            }
+
+            // a pawn that just moved two and gives check can still be taken in passing
+            if let Some(en_passant_square) = board.en_passant_chance {
+                if attack_board & square_bitboard(en_passant_square) != 0 &&
+                checkers & square_bitboard(side_to_move.opposite().offset_rank(en_passant_square)) != 0 {
+                    moves.push(Move::new_en_passant(board, pawn, en_passant_square));
                 }
             }
+
+            if board.get_piece(forward_square) != Empty {
+                continue;
+            }
+
+            if block_squares & square_bitboard(forward_square) != 0 {
+                add_pawn_moves(moves, board, pawn, forward_square, side_to_move as u8, is_promo);
+            }
+
+            let double_move_square = side_to_move.offset_rank(forward_square);
+
+            if is_back_two_ranks(side_to_move, pawn) && block_squares & square_bitboard(double_move_square) != 0 &&
+            board.get_piece(double_move_square) == Empty {
+                moves.push(Move::new_pawn_double(board, pawn, double_move_square));
+            }
+        }
+
+        for knight in board.get_piece_squares((side_to_move as u8 | KNIGHT).into()) {
+            add_moves_from_bitboard(moves, board, knight, get_knight_move_board(knight) & target_squares);
+        }
+
+        for bishop in board.get_piece_squares((side_to_move as u8 | BISHOP).into()) {
+            add_bishop_moves(moves, board, bishop, target_squares);
+        }
+
+        for rook in board.get_piece_squares((side_to_move as u8 | ROOK).into()) {
+            add_rook_moves(moves, board, rook, target_squares);
+        }
+
+        for queen in board.get_piece_squares((side_to_move as u8 | QUEEN).into()) {
+            add_bishop_moves(moves, board, queen, target_squares);
+            add_rook_moves(moves, board, queen, target_squares);
         }
+
+        // nothing dropped can take the checker, only get in its way
+        if board.variant == Variant::Crazyhouse {
+            add_drop_moves(moves, board, side_to_move, block_squares);
+        }
+
+        // pinned pieces can't leave their line, which never crosses the check
+        moves.retain(|evasion| {
+
+            if evasion.move_type == MoveType::EnPassant {
+                return is_legal_en_passant(board, evasion);
+            }
+
+            position_info.pinned_pieces.get_safe_squares(evasion.start_square) & square_bitboard(evasion.end_square) != 0
+
+        });
     }
 
-    legal_moves
+    add_moves_from_bitboard(
+        moves,
+        board,
+        king_square,
+        get_king_move_board(king_square) & !board.get_colour_bitboard(side_to_move) & !position_info.opponent_attacked_squares
+    );
+}
+
+// en passant takes two pawns off their squares at once, which pins along the rank
+// and checks can't describe, so it's played out on the occupancy instead
+fn is_legal_en_passant(board: &Board, en_passant_move: &Move) -> bool {
+
+    let colour = board.side_to_move;
+    let captured_board = square_bitboard(colour.opposite().offset_rank(en_passant_move.end_square));
+
+    let occupied = board.get_occupied_bitboard() & !square_bitboard(en_passant_move.start_square) & !captured_board |
+        square_bitboard(en_passant_move.end_square);
+
+    get_attackers(board, board.get_king_square(colour), colour.opposite(), occupied) & !captured_board == 0
 
 }
 
+// the pseudo-legal moves of the piece on one square, castles left out
+fn add_square_moves(moves: &mut MoveList, board: &Board, square: Square) {
+
+    let colour = board.side_to_move;
+    let target_squares = get_target_squares(board, colour, GenType::All);
+
+    match board.get_piece(square) as u8 & 0b111 {
+        PAWN => gen_valid_pawn_moves(moves, board, square, colour, GenType::All),
+        KNIGHT => add_moves_from_bitboard(moves, board, square, get_knight_move_board(square) & target_squares),
+        BISHOP => add_bishop_moves(moves, board, square, target_squares),
+        ROOK => add_rook_moves(moves, board, square, target_squares),
+        QUEEN => {
+            add_bishop_moves(moves, board, square, target_squares);
+            add_rook_moves(moves, board, square, target_squares);
+        }
+        // atomic kings can't capture
+        KING if board.variant == Variant::Atomic => add_moves_from_bitboard(moves, board, square, get_king_move_board(square) & !board.get_occupied_bitboard()),
+        KING => add_moves_from_bitboard(moves, board, square, get_king_move_board(square) & target_squares),
+        _ => {}
+    }
+}
+
+// whether a move made for this board, like an unpacked hash move, can be played,
+// worked out for that move alone rather than by generating all the others
+pub fn is_legal(board: &Board, possible_move: &Move) -> bool {
+
+    if board.variant != Variant::Standard && board.get_variant_win().is_some() {
+        return false;
+    }
+
+    let colour = board.side_to_move;
+    let own_king = board.get_piece_bitboard((colour as u8 | KING).into());
+
+    let mut pseudo_legal_moves = MoveList::new();
+
+    match possible_move.move_type {
+        MoveType::Null => return false,
+        MoveType::Drop(piece) => {
+
+            let pawn_on_back_rank = piece.is_pawn() && square_bitboard(possible_move.end_square) & BACK_RANKS != 0;
+
+            if board.variant != Variant::Crazyhouse || piece.get_colour() != colour || piece.is_king() ||
+            board.get_pocket_count(piece) == 0 || board.get_piece(possible_move.end_square) != Empty || pawn_on_back_rank {
+                return false;
+            }
+
+            pseudo_legal_moves.push(*possible_move);
+
+        }
+        MoveType::Castle if own_king == 0 => return false,
+        MoveType::Castle if board.variant == Variant::Atomic => {
+            if !is_in_check(board, colour) {
+                add_castling_moves(&mut pseudo_legal_moves, board, colour, get_atomic_attacked_squares(board, colour));
+            }
+        }
+        MoveType::Castle => add_castling_moves(&mut pseudo_legal_moves, board, colour, get_position_info(board, colour).opponent_attacked_squares),
+        _ => {
+
+            let moved_piece = board.get_piece(possible_move.start_square);
+
+            if moved_piece == Empty || moved_piece.get_colour() != colour || moved_piece != possible_move.moved_piece {
+                return false;
+            }
+
+            add_square_moves(&mut pseudo_legal_moves, board, possible_move.start_square);
+
+        }
+    }
+
+    if !pseudo_legal_moves.iter().any(|pseudo_legal_move|
+        pseudo_legal_move.pack() == possible_move.pack() && pseudo_legal_move.replaced_piece == possible_move.replaced_piece
+    ) {
+        return false;
+    }
+
+    if board.variant == Variant::Atomic {
+        return leaves_atomic_king_safe(&mut board.clone(), possible_move);
+    }
+
+    // nothing to leave in check
+    if own_king == 0 {
+        return true;
+    }
+
+    match possible_move.move_type {
+        // add_castling_moves only gives the legal ones
+        MoveType::Castle => true,
+        MoveType::EnPassant => is_legal_en_passant(board, possible_move),
+        _ => {
+
+            let king_square = if possible_move.moved_piece.is_king() {possible_move.end_square} else {board.get_king_square(colour)};
+            let end_board = square_bitboard(possible_move.end_square);
+            let occupied = board.get_occupied_bitboard() & !square_bitboard(possible_move.start_square) | end_board;
+
+            // whatever was taken can't attack any more
+            get_attackers(board, king_square, colour.opposite(), occupied) & !end_board == 0
+
+        }
+    }
+}
+
+fn add_variant_moves(moves: &mut MoveList, board: &Board, gen_type: GenType) {
+
+    if board.get_variant_win().is_some() {
+        return;
+    }
+
+    let side_to_move = board.side_to_move;
+
+    match board.variant {
+        // atomic tries every move out anyway, so splitting them afterwards costs little extra
+        Variant::Atomic => {
+            add_atomic_moves(moves, board);
+            match gen_type {
+                GenType::All => {}
+                GenType::Captures => moves.retain(is_noisy),
+                GenType::Quiets => moves.retain(|atomic_move| !is_noisy(atomic_move))
+            }
+        }
+        // nothing to leave in check, so anything goes
+        _ if board.get_piece_bitboard((side_to_move as u8 | KING).into()) == 0 => add_piece_moves(moves, board, side_to_move, gen_type),
+        _ => add_standard_moves(moves, board, gen_type)
+    }
+}
+
+// explosions make pins and checks too irregular to work out ahead of time,
+// so each pseudo-legal move is tried out instead
+fn add_atomic_moves(moves: &mut MoveList, board: &Board) {
+
+    let side_to_move = board.side_to_move;
+    let king_square = board.get_king_square(side_to_move);
+
+    add_piece_moves(moves, board, side_to_move, GenType::All);
+
+    let own_castling_rights = get_castling_right(side_to_move, true) | get_castling_right(side_to_move, false);
+
+    if board.castling_rights & own_castling_rights != NO_CASTLING_RIGHTS && !is_in_check(board, side_to_move) {
+        add_castling_moves(moves, board, side_to_move, get_atomic_attacked_squares(board, side_to_move));
+    }
+
+    // kings can't capture
+    add_moves_from_bitboard(moves, board, king_square, get_king_move_board(king_square) & !board.get_occupied_bitboard());
+
+    let mut test_board = board.clone();
+
+    moves.retain(|possible_move| leaves_atomic_king_safe(&mut test_board, possible_move));
+}
+
+// the king has to survive the explosion, and can only be left in check if the other one didn't
+fn leaves_atomic_king_safe(test_board: &mut Board, possible_move: &Move) -> bool {
+
+    let side_to_move = test_board.side_to_move;
+
+    test_board.make_move(possible_move);
+
+    let is_legal = test_board.get_piece_bitboard((side_to_move as u8 | KING).into()) != 0 &&
+        (test_board.get_variant_win() == Some(side_to_move) || !is_in_check(test_board, side_to_move));
+
+    test_board.undo_move();
+
+    is_legal
+
+}
+
+// squares the king can't pass through while castling, next to the enemy king is safe in atomic
+fn get_atomic_attacked_squares(board: &Board, colour: Colour) -> Bitboard {
+
+    let opp_king_board = board.get_piece_bitboard((colour.opposite() as u8 | KING).into());
+    let mut attacked_squares = 0;
+
+    for square in VALID_SQUARES {
+
+        let square_board = square_bitboard(square);
+
+        if get_king_move_board(square) & opp_king_board == 0 && get_attackers(board, square, colour.opposite(), board.get_occupied_bitboard()) & !opp_king_board != 0 {
+            attacked_squares |= square_board;
+        }
+    }
+
+    attacked_squares
+
+}
+
+pub fn get_attackers(board: &Board, square: Square, colour: Colour, occupied: Bitboard) -> Bitboard {
+
+    let colour_code = colour as u8;
+    let queens = board.get_piece_bitboard((QUEEN | colour_code).into());
+
+    get_pawn_attack_board(square, colour.opposite()) & board.get_piece_bitboard((PAWN | colour_code).into()) |
+    get_knight_move_board(square) & board.get_piece_bitboard((KNIGHT | colour_code).into()) |
+    get_king_move_board(square) & board.get_piece_bitboard((KING | colour_code).into()) |
+    get_bishop_attack_board(square, occupied) & (board.get_piece_bitboard((BISHOP | colour_code).into()) | queens) |
+    get_rook_attack_board(square, occupied) & (board.get_piece_bitboard((ROOK | colour_code).into()) | queens)
+
+}
+
+// whether the opponent is in check once the move is made, worked out without making it
+pub fn gives_check(board: &Board, possible_move: &Move) -> bool {
+
+    let colour = board.side_to_move;
+    let opp_king = board.get_piece_bitboard((colour.opposite() as u8 | KING).into());
+
+    if opp_king == 0 {
+        return false;
+    }
+
+    let opp_king_square = index_to_square(opp_king.trailing_zeros() as usize);
+    let start_board = square_bitboard(possible_move.start_square);
+
+    // where the moving piece, and a castling rook, end up
+    let (placed_piece, placed_square, rook_square) = match possible_move.move_type {
+        MoveType::Castle => {
+            let castling_right = get_castling_right(colour, possible_move.end_square > possible_move.start_square);
+            let (king_end_square, rook_end_square) = get_castling_end_squares(castling_right);
+            (possible_move.moved_piece, king_end_square, Some(rook_end_square))
+        }
+        MoveType::Promotion(promote_piece) => (promote_piece, possible_move.end_square, None),
+        MoveType::Null => return false,
+        _ => (possible_move.moved_piece, possible_move.end_square, None)
+    };
+
+    // atomic kings next to each other can't be checked
+    if board.variant == Variant::Atomic {
+
+        let own_king = if placed_piece.is_king() {
+            square_bitboard(placed_square)
+        }
+        else {
+            board.get_piece_bitboard((colour as u8 | KING).into())
+        };
+
+        if get_king_move_board(opp_king_square) & own_king != 0 {
+            return false;
+        }
+
+        // a capture blows itself and everything but pawns around it away,
+        // so only what's left standing can give check
+        if possible_move.is_capture() {
+
+            let occupied = board.get_occupied_bitboard();
+            let pawns = board.get_piece_bitboard(WhitePawn) | board.get_piece_bitboard(BlackPawn);
+            let mut blast = get_king_move_board(possible_move.end_square) & occupied & !pawns | square_bitboard(possible_move.end_square) | start_board;
+
+            if possible_move.move_type == MoveType::EnPassant {
+                blast |= square_bitboard(colour.opposite().offset_rank(possible_move.end_square));
+            }
+
+            if blast & opp_king != 0 {
+                return false;
+            }
+
+            let occupied = occupied & !blast;
+            return get_attackers(board, opp_king_square, colour, occupied) & occupied != 0;
+
+        }
+    }
+
+    let mut occupied = board.get_occupied_bitboard() & !start_board | square_bitboard(placed_square);
+    let mut moved_away = start_board;
+
+    match possible_move.move_type {
+        MoveType::Castle => {
+            let rook_board = square_bitboard(possible_move.end_square);
+            occupied = occupied & !rook_board | square_bitboard(rook_square.unwrap());
+            moved_away |= rook_board;
+        }
+        MoveType::EnPassant => occupied &= !square_bitboard(colour.opposite().offset_rank(possible_move.end_square)),
+        _ => {}
+    }
+
+    // touching atomic kings hide every check, so stepping apart can show any of them
+    if board.variant == Variant::Atomic && placed_piece.is_king() && get_king_move_board(opp_king_square) & start_board != 0 &&
+    get_attackers(board, opp_king_square, colour, occupied) & occupied & !moved_away != 0 {
+        return true;
+    }
+
+    // pieces that stayed put but now see the king
+    let colour_code = colour as u8;
+    let queens = board.get_piece_bitboard((QUEEN | colour_code).into());
+    let diagonal_pieces = (board.get_piece_bitboard((BISHOP | colour_code).into()) | queens) & !moved_away;
+    let orthogonal_pieces = (board.get_piece_bitboard((ROOK | colour_code).into()) | queens) & !moved_away;
+
+    if get_bishop_attack_board(opp_king_square, occupied) & diagonal_pieces != 0 ||
+    get_rook_attack_board(opp_king_square, occupied) & orthogonal_pieces != 0 {
+        return true;
+    }
+
+    if let Some(rook_square) = rook_square {
+        return get_rook_attack_board(rook_square, occupied) & opp_king != 0;
+    }
+
+    let attack_board = match placed_piece as u8 & 0b111 {
+        PAWN => get_pawn_attack_board(placed_square, colour),
+        KNIGHT => get_knight_move_board(placed_square),
+        BISHOP => get_bishop_attack_board(placed_square, occupied),
+        ROOK => get_rook_attack_board(placed_square, occupied),
+        QUEEN => get_queen_attack_board(placed_square, occupied),
+        _ => 0
+    };
+
+    attack_board & opp_king != 0
+
+}
+
+// false for a side without a king, and in atomic when the kings touch
+pub fn is_in_check(board: &Board, colour: Colour) -> bool {
+    get_checkers(board, colour) != 0
+}
+
+// the pieces giving check to the colour's king
+pub fn get_checkers(board: &Board, colour: Colour) -> Bitboard {
+
+    if board.get_piece_bitboard((colour as u8 | KING).into()) == 0 {
+        return 0;
+    }
+
+    let king_square = board.get_king_square(colour);
+    let mut attackers = get_attackers(board, king_square, colour.opposite(), board.get_occupied_bitboard());
+
+    if board.variant == Variant::Atomic {
+
+        let opp_king_board = board.get_piece_bitboard((colour.opposite() as u8 | KING).into());
+
+        if get_king_move_board(king_square) & opp_king_board != 0 {
+            return 0;
+        }
+
+        attackers &= !opp_king_board;
+
+    }
+
+    attackers
+
+}
+
+// the colour's pieces that can't leave the line between their king and an enemy slider,
+// without the attack map get_position_info works out
+pub fn get_pinned_pieces(board: &Board, colour: Colour) -> Bitboard {
+
+    if board.get_piece_bitboard((colour as u8 | KING).into()) == 0 {
+        return 0;
+    }
+
+    let king_square = board.get_king_square(colour);
+    let opp_colour = colour.opposite() as u8;
+    let opp_queens = board.get_piece_bitboard((QUEEN | opp_colour).into());
+
+    let snipers = get_bishop_attack_board(king_square, 0) & (board.get_piece_bitboard((BISHOP | opp_colour).into()) | opp_queens) |
+        get_rook_attack_board(king_square, 0) & (board.get_piece_bitboard((ROOK | opp_colour).into()) | opp_queens);
+
+    let occupied = board.get_occupied_bitboard();
+    let own_pieces = board.get_colour_bitboard(colour);
+    let mut pinned_pieces = 0;
+
+    for sniper_square in bitboard_squares(snipers) {
+
+        let blockers = get_between_board(king_square, sniper_square) & occupied;
+
+        if blockers.count_ones() == 1 && blockers & own_pieces != 0 {
+            pinned_pieces |= blockers;
+        }
+    }
+
+    pinned_pieces
+
+}
+
+// the same questions asked of the board, for when the whole position info is too much
+impl Board {
+
+    pub fn attackers_to(&self, square: Square, colour: Colour) -> Bitboard {
+        get_attackers(self, square, colour, self.get_occupied_bitboard())
+    }
+
+    // only ever the side to move, is_in_check takes either colour
+    pub fn is_in_check(&self) -> bool {
+        is_in_check(self, self.side_to_move)
+    }
+
+    pub fn checkers(&self) -> Bitboard {
+        get_checkers(self, self.side_to_move)
+    }
+
+    pub fn gives_check(&self, possible_move: &Move) -> bool {
+        gives_check(self, possible_move)
+    }
+
+    pub fn is_legal(&self, possible_move: &Move) -> bool {
+        is_legal(self, possible_move)
+    }
+
+    pub fn pinned_pieces(&self, colour: Colour) -> Bitboard {
+        get_pinned_pieces(self, colour)
+    }
+}
+
 pub struct PositionInfo {
-    pub pinned_pieces: Vec<(Square, u128)>,
+    pub pinned_pieces: PinList,
     pub king_attacker_count: u32,
-    pub king_block_board: u128,
-    pub opponent_attacked_squares: u128
+    pub king_block_board: Bitboard,
+    pub opponent_attacked_squares: Bitboard
 }
 
 pub fn get_position_info(board: &Board, colour: Colour) -> PositionInfo {
     
-    let mut pinned_pieces = Vec::new();
+    let mut pinned_pieces = PinList::new();
     let mut king_attacker_count = 0;
     let mut king_block_board = 0;
 
-    let king_square = match colour {
-        White => board.white_king,
-        Black => board.black_king
-    };
+    let king_square = board.get_king_square(colour);
 
-    let mut opponent_attacked_squares = get_king_move_board(match colour {
-        White => board.black_king,
-        Black => board.white_king
-    });
+    // horde's white side has no king to attack with
+    let mut opponent_attacked_squares = board.get_piece_squares((colour.opposite() as u8 | KING).into())
+        .fold(0, |attacked_squares, opp_king_square| attacked_squares | get_king_move_board(opp_king_square));
 
-    let king_square_board = 1 << king_square;
+    let king_square_board = square_bitboard(king_square);
 
     let opp_colour = colour.opposite() as u8;
 
     let opp_pawn = (PAWN | opp_colour).into();
 
-    for pawn_square in board.get_piece_position(opp_pawn) {
+    for pawn_square in board.get_piece_squares(opp_pawn) {
 
-        let attack_board = get_pawn_attack_board(*pawn_square, colour.opposite());
+        let attack_board = get_pawn_attack_board(pawn_square, colour.opposite());
         opponent_attacked_squares |= attack_board;
 
         if attack_board & king_square_board != 0 {
-            king_block_board = 1 << pawn_square;
+            king_block_board = square_bitboard(pawn_square);
         }
     }
 
@@ -318,13 +887,13 @@ pub fn get_position_info(board: &Board, colour: Colour) -> PositionInfo {
     let opp_knight = KNIGHT | opp_colour;
     let mut knight_attack_board = 0;
 
-    for knight_square in board.get_piece_position(opp_knight.into()) {
+    for knight_square in board.get_piece_squares(opp_knight.into()) {
 
-        let attack_board = get_knight_move_board(*knight_square);
+        let attack_board = get_knight_move_board(knight_square);
         knight_attack_board |= attack_board;
 
         if attack_board & king_square_board != 0 {
-            king_block_board |= 1 << knight_square;
+            king_block_board |= square_bitboard(knight_square);
         }
     }
 
@@ -338,140 +907,62 @@ pub fn get_position_info(board: &Board, colour: Colour) -> PositionInfo {
     let opp_queen = QUEEN | opp_colour;
     let opp_rook = ROOK | opp_colour;
 
-    let opp_bishop_positions = board.get_piece_position(opp_bishop.into());
-    let opp_rook_positions = board.get_piece_position(opp_rook.into());
-    let opp_queen_positions = board.get_piece_position(opp_queen.into());
-
-    let opp_diagonal_positions = opp_bishop_positions
-        .iter()
-        .chain(opp_queen_positions)
-        .cloned();
-    
-    let opp_orthogonal_positions = opp_rook_positions
-        .iter()
-        .chain(opp_queen_positions)
-        .cloned();
+    let opp_queens = board.get_piece_bitboard(opp_queen.into());
 
-    opponent_attacked_squares |= calc_sliding_boards(
-        board,
-        opp_diagonal_positions,
-        &DIAGONAL_OFFSETS,
-        king_square,
-        &mut king_block_board,
-        &mut king_attacker_count,
-        &mut pinned_pieces
-    );
+    let opp_diagonal_pieces = board.get_piece_bitboard(opp_bishop.into()) | opp_queens;
+    let opp_orthogonal_pieces = board.get_piece_bitboard(opp_rook.into()) | opp_queens;
 
-    opponent_attacked_squares |= calc_sliding_boards(
-        board,
-        opp_orthogonal_positions,
-        &ORTHOGONAL_OFFSETS,
-        king_square,
-        &mut king_block_board,
-        &mut king_attacker_count,
-        &mut pinned_pieces
-    );
+    let occupied = board.get_occupied_bitboard();
+    
+    // the king can't hide from a slider by stepping back along its line
+    let occupied_without_king = occupied & !king_square_board;
 
-    PositionInfo {
-        pinned_pieces,
-        king_attacker_count,
-        king_block_board,
-        opponent_attacked_squares
+    for piece_square in bitboard_squares(opp_diagonal_pieces) {
+        opponent_attacked_squares |= get_bishop_attack_board(piece_square, occupied_without_king);
     }
-}
-
-#[derive(PartialEq, Eq)]
-enum PinAttack {
-    NoneFound,
-    Pin(Square),
-    DoubleBlocked,
-    ThroughKing
-}
 
-#[inline(always)]
-fn calc_sliding_boards(
-    board: &Board, piece_squares: impl Iterator<Item = Square>, offsets: &[i8; 4], king_square: Square, king_block_board: &mut u128,
-    king_attacker_count: &mut u32, pinned_pieces: &mut Vec<(Square, u128)>
-) -> u128 {
+    for piece_square in bitboard_squares(opp_orthogonal_pieces) {
+        opponent_attacked_squares |= get_rook_attack_board(piece_square, occupied_without_king);
+    }
 
-    let mut attack_board = 0;
+    let own_pieces = board.get_colour_bitboard(colour);
 
-    for piece_square in piece_squares {
+    let snipers = get_bishop_attack_board(king_square, 0) & opp_diagonal_pieces |
+        get_rook_attack_board(king_square, 0) & opp_orthogonal_pieces;
 
-        for &dir in offsets {
-        
-            let mut test_square = piece_square;
-            let mut line_squares = 0;
-            let mut pin = PinAttack::NoneFound;
-        
-            loop {
-                
-                test_square = test_square.wrapping_add_signed(dir);
-                
-                if !square_is_on_board(test_square) {
-                    break;
-                }
+    for sniper_square in bitboard_squares(snipers) {
 
-                line_squares |= 1 << test_square;
-    
-                if test_square == king_square {
-                    
-                    match pin {
-                        PinAttack::NoneFound => {
-                            *king_block_board |= line_squares | 1 << piece_square;
-                            *king_attacker_count += 1;
-                            pin = PinAttack::ThroughKing;
-                        }
-                        PinAttack::Pin(pin_square) => {
-                            pinned_pieces.push((pin_square, line_squares | 1 << piece_square))
-                        }
-                        _ => {}
-                    }
-
-                    continue;
+        let line_squares = get_between_board(king_square, sniper_square);
+        let blockers = line_squares & occupied;
 
-                }
-    
-                let piece = board.get_piece(test_square);
-    
-                if piece == Empty {
-                }
-                else if piece.is_colour(board.side_to_move) {
-                    
-                    pin = match pin {
-                        PinAttack::NoneFound => {
-                            attack_board |= line_squares;
-                            PinAttack::Pin(test_square)
-                        }
-                        PinAttack::Pin(_) => PinAttack::DoubleBlocked,
-                        _ => pin
-                    };
-                }
-                else {
-                    break;
-                }
-            }
-
-            if pin == PinAttack::NoneFound || pin == PinAttack::ThroughKing {
-                attack_board |= line_squares;
-            }
+        if blockers == 0 {
+            king_block_board |= line_squares | square_bitboard(sniper_square);
+            king_attacker_count += 1;
+        }
+        else if blockers.count_ones() == 1 && blockers & own_pieces != 0 {
+            pinned_pieces.push(index_to_square(blockers.trailing_zeros() as usize), line_squares | square_bitboard(sniper_square));
         }
     }
 
-    attack_board
-
+    PositionInfo {
+        pinned_pieces,
+        king_attacker_count,
+        king_block_board,
+        opponent_attacked_squares
+    }
 }
 
-pub fn get_num_moves(board: &mut Board, depth: u32) -> u32 {
+pub fn get_num_moves(board: &mut Board, depth: u32) -> u64 {
 
     if depth == 0 {
         return 1;
     }
 
-    let possible_moves = get_possible_moves(board);
+    let mut possible_moves = MoveList::new();
+    generate_moves(&mut possible_moves, board, GenType::All);
     
     if depth == 1 {
-        return possible_moves.len() as u32;
+        return possible_moves.len() as u64;
     }
 
     let mut moves = 0;
@@ -0,0 +1,240 @@
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+
+use super::chess_util::*;
+use super::r#move::*;
+
+// no standard position has more than 218 legal moves, so this is plenty
+pub const MAX_MOVES: usize = 256;
+
+// most pinned pieces a king can have, one per line to it
+pub const MAX_PINS: usize = 8;
+
+// moves kept on the stack so generating them never touches the allocator.
+// standard chess tops out at 218 legal moves, but a crazyhouse pocket of every piece
+// over an empty board drops onto nearly 300 squares, so rather than panic those spill onto the heap
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [MaybeUninit<Move>; MAX_MOVES],
+    len: usize,
+    overflow: Vec<Move>
+}
+
+impl MoveList {
+
+    pub fn new() -> Self {
+        Self {
+            moves: [MaybeUninit::uninit(); MAX_MOVES],
+            len: 0,
+            overflow: Vec::new()
+        }
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, new_move: Move) {
+
+        if self.overflow.is_empty() {
+
+            if self.len < MAX_MOVES {
+                self.moves[self.len].write(new_move);
+                self.len += 1;
+                return;
+            }
+
+            // everything moves over at once so the moves stay in one slice
+            let mut overflow = Vec::with_capacity(MAX_MOVES * 2);
+            overflow.extend_from_slice(self.inline_moves());
+            self.overflow = overflow;
+            self.len = 0;
+
+        }
+
+        self.overflow.push(new_move);
+
+    }
+
+    // whether the moves outgrew the stack and went to the heap
+    pub fn spilled(&self) -> bool {
+        !self.overflow.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.overflow.clear();
+    }
+
+    pub fn retain<F: FnMut(&Move) -> bool>(&mut self, mut keep: F) {
+
+        if !self.overflow.is_empty() {
+            self.overflow.retain(keep);
+            return;
+        }
+
+        let mut kept = 0;
+
+        for idx in 0..self.len {
+
+            let possible_move = unsafe {self.moves[idx].assume_init()};
+
+            if keep(&possible_move) {
+                self.moves[kept].write(possible_move);
+                kept += 1;
+            }
+        }
+
+        self.len = kept;
+
+    }
+
+    // keeps the order of what's left, unlike swap_remove on the slice
+    pub fn remove(&mut self, idx: usize) -> Move {
+
+        if !self.overflow.is_empty() {
+            return self.overflow.remove(idx);
+        }
+
+        let removed_move = self[idx];
+        self[idx..].rotate_left(1);
+        self.len -= 1;
+
+        removed_move
+
+    }
+
+    pub fn insert(&mut self, idx: usize, new_move: Move) {
+        self.push(new_move);
+        self[idx..].rotate_right(1);
+    }
+
+    fn inline_moves(&self) -> &[Move] {
+        unsafe {std::slice::from_raw_parts(self.moves.as_ptr() as *const Move, self.len)}
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        if self.overflow.is_empty() {
+            self.inline_moves()
+        }
+        else {
+            &self.overflow
+        }
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        if self.overflow.is_empty() {
+            unsafe {std::slice::from_raw_parts_mut(self.moves.as_mut_ptr() as *mut Move, self.len)}
+        }
+        else {
+            &mut self.overflow
+        }
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(moves: I) -> Self {
+        let mut list = Self::new();
+        moves.into_iter().for_each(|new_move| list.push(new_move));
+        list
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MoveListIntoIter {list: self, idx: 0}
+    }
+}
+
+pub struct MoveListIntoIter {
+    list: MoveList,
+    idx: usize
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let next_move = self.list.get(self.idx).copied();
+        self.idx += 1;
+        next_move
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len().saturating_sub(self.idx);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+// each pinned piece with the squares it can still move to
+#[derive(Clone, Copy, Debug)]
+pub struct PinList {
+    pins: [(Square, Bitboard); MAX_PINS],
+    len: usize
+}
+
+impl PinList {
+
+    pub fn new() -> Self {
+        Self {
+            pins: [(0, 0); MAX_PINS],
+            len: 0
+        }
+    }
+
+    pub fn push(&mut self, pinned_square: Square, safe_squares: Bitboard) {
+        self.pins[self.len] = (pinned_square, safe_squares);
+        self.len += 1;
+    }
+
+    // all of the board when the piece isn't pinned
+    #[inline(always)]
+    pub fn get_safe_squares(&self, square: Square) -> Bitboard {
+        self.iter()
+            .find(|(pinned_square, _)| *pinned_square == square)
+            .map_or(!0, |(_, safe_squares)| *safe_squares)
+    }
+}
+
+impl Default for PinList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for PinList {
+    type Target = [(Square, Bitboard)];
+
+    fn deref(&self) -> &[(Square, Bitboard)] {
+        &self.pins[..self.len]
+    }
+}
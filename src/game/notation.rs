@@ -0,0 +1,110 @@
+use super::piece::*;
+use super::board::*;
+use super::chess_util::*;
+use super::r#move::*;
+use super::san::get_san_body;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveFormat {
+    // Nf3, exd5, e8=Q+
+    San,
+    // g1f3, e7e8q
+    Uci,
+    // Ng1-f3, e4xd5, e7-e8=Q+
+    Lan,
+    // ♘f3, e8=♕+, the pieces in the mover's colour
+    Figurine,
+    // 7163, 57581 for e7e8q, castles are the king's move, or the king taking its rook in chess960
+    Iccf
+}
+
+// iccf numbers promotions from the queen down
+const ICCF_PROMOTIONS: [char; 4] = ['q', 'r', 'b', 'n'];
+
+impl MoveFormat {
+    pub const ALL: [MoveFormat; 5] = [MoveFormat::San, MoveFormat::Uci, MoveFormat::Lan, MoveFormat::Figurine, MoveFormat::Iccf];
+}
+
+fn get_lan_body(lan_move: &Move) -> String {
+
+    if lan_move.is_null() || lan_move.is_castle() || lan_move.is_drop() {
+        return get_san_body(lan_move, &[]);
+    }
+
+    format!(
+        "{}{}{}{}{}",
+        if lan_move.moved_piece.is_pawn() {String::new()} else {lan_move.moved_piece.to_an_char().to_string()},
+        square_to_an(lan_move.start_square),
+        if lan_move.is_capture() {'x'} else {'-'},
+        square_to_an(lan_move.end_square),
+        lan_move.promotion_piece().map_or(String::new(), |promote_piece| format!("={}", promote_piece.to_an_char()))
+    )
+}
+
+// drops and null moves have no number form, so they stay as they are in uci
+fn uci_to_iccf(uci: &str) -> String {
+
+    if uci == "0000" || uci.contains('@') {
+        return uci.to_string();
+    }
+
+    uci.chars().enumerate().map(|(idx, c)| match idx {
+        0 | 2 => (b'1' + c as u8 - b'a') as char,
+        4 => (b'1' + ICCF_PROMOTIONS.iter().position(|&promotion| promotion == c).unwrap() as u8) as char,
+        _ => c
+    }).collect()
+}
+
+fn iccf_to_uci(iccf: &str) -> Result<String, MoveParseError> {
+
+    if iccf == "0000" || iccf.contains('@') {
+        return Ok(iccf.to_string());
+    }
+
+    let chars: Vec<char> = iccf.chars().collect();
+
+    if chars.len() != 4 && chars.len() != 5 {
+        return Err(MoveParseError::BadLength(chars.len()));
+    }
+
+    chars.iter().enumerate().map(|(idx, &c)| match idx {
+        4 => match c {
+            '1'..='4' => Ok(ICCF_PROMOTIONS[c as usize - '1' as usize]),
+            _ => Err(MoveParseError::BadPromotion(c))
+        },
+        _ => match c {
+            '1'..='8' if idx % 2 == 0 => Ok((b'a' + c as u8 - b'1') as char),
+            '1'..='8' => Ok(c),
+            _ => Err(MoveParseError::BadSquare)
+        }
+    }).collect()
+}
+
+impl Board {
+
+    pub fn format_move(&self, format_move: &Move, format: MoveFormat) -> String {
+        match format {
+            MoveFormat::San => self.san(format_move),
+            MoveFormat::Uci => format_move.as_uci(self.chess960),
+            MoveFormat::Lan => get_lan_body(format_move) + self.get_check_suffix(format_move),
+            MoveFormat::Figurine => self.san(format_move).chars().map(|c| match Piece::try_from_char(c) {
+                Some(piece) if c.is_ascii_uppercase() => Piece::from(piece as u8 | self.side_to_move as u8).to_figurine(),
+                _ => c
+            }).collect(),
+            MoveFormat::Iccf => uci_to_iccf(&format_move.as_uci(self.chess960))
+        }
+    }
+
+    // san, long algebraic and figurines all go through parse_san, so they're as forgiving as it is
+    pub fn parse_move(&self, text: &str, format: MoveFormat) -> Result<Move, MoveParseError> {
+        match format {
+            MoveFormat::San | MoveFormat::Lan => self.parse_san(text).map_err(MoveParseError::BadSan),
+            MoveFormat::Uci => self.parse_uci_move(text),
+            MoveFormat::Figurine => {
+                let san: String = text.chars().map(|c| Piece::from_figurine(c).map_or(c, Piece::to_an_char)).collect();
+                self.parse_san(&san).map_err(MoveParseError::BadSan)
+            }
+            MoveFormat::Iccf => self.parse_uci_move(&iccf_to_uci(text.trim())?)
+        }
+    }
+}
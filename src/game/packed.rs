@@ -0,0 +1,342 @@
+#![allow(non_upper_case_globals)]
+use std::io::{self, Read, Write};
+
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::variant::*;
+
+// the nibbles past the twelve plain pieces carry the state that belongs to a square
+const EN_PASSANT_PAWN: u8 = 12;
+const WHITE_CASTLING_ROOK: u8 = 13;
+const BLACK_CASTLING_ROOK: u8 = 14;
+
+const BLACK_TO_MOVE_FLAG: u8 = 0b01;
+const CHESS960_FLAG: u8 = 0b10;
+
+const SCORE_FLAG: u8 = 0b001;
+const OUTCOME_SHIFT: u8 = 1;
+
+// bytes 0..8 are the occupied squares, 8..24 a nibble per piece in square order,
+// then flags, variant, remaining checks, a spare byte and the two move counters.
+// there's only room for 32 pieces on the board and none in hand, so horde's opening
+// and crazyhouse pockets or promoted pieces can't be packed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PackedBoard(pub [u8; 32]);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PackError {
+    TooManyPieces(u32),
+    PocketsNotEmpty,
+    BadEnPassant,
+    BadCastling(CastlingRights),
+    CounterTooLarge,
+    BadPieceCode(u8),
+    BadVariant(u8),
+    MissingKing(Colour)
+}
+
+impl std::fmt::Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::TooManyPieces(count) => write!(f, "{} pieces don't fit, 32 is the most", count),
+            Self::PocketsNotEmpty => write!(f, "crazyhouse pockets and promoted pieces can't be packed"),
+            Self::BadEnPassant => write!(f, "en passant square without a pawn that just moved two"),
+            Self::BadCastling(_) => write!(f, "castling right without a rook to castle with"),
+            Self::CounterTooLarge => write!(f, "move counter doesn't fit in 16 bits"),
+            Self::BadPieceCode(code) => write!(f, "{} is not a packed piece", code),
+            Self::BadVariant(index) => write!(f, "{} is not a variant", index),
+            Self::MissingKing(colour) => write!(f, "{:?} has no king", colour)
+        }
+    }
+}
+
+impl std::error::Error for PackError {}
+
+fn piece_to_code(piece: Piece) -> u8 {
+    ((piece as u8 & 0b111) << 1) | (piece as u8 >> 3)
+}
+
+fn code_to_piece(code: u8) -> Piece {
+    ((code >> 1) | ((code & 1) << 3)).into()
+}
+
+impl Board {
+
+    // packs everything a fen holds into 32 bytes, the move history isn't kept.
+    // a board with more than 32 pieces, or crazyhouse pieces in hand or promoted,
+    // is an error rather than being packed with something missing
+    pub fn encode(&self) -> Result<PackedBoard, PackError> {
+
+        let occupied = self.get_occupied_bitboard();
+        let piece_count = occupied.count_ones();
+
+        if piece_count > 32 {
+            return Err(PackError::TooManyPieces(piece_count));
+        }
+
+        if POCKET_PIECES.into_iter().any(|piece| self.get_pocket_count(piece) != 0) ||
+        bitboard_squares(occupied).any(|square| self.is_promoted(square)) {
+            return Err(PackError::PocketsNotEmpty);
+        }
+
+        let mut codes: [u8; 128] = [0; 128];
+
+        for square in bitboard_squares(occupied) {
+            codes[square as usize] = piece_to_code(self.get_piece(square));
+        }
+
+        if let Some(en_passant_square) = self.en_passant_chance {
+
+            let pawn_square = self.side_to_move.opposite().offset_rank(en_passant_square);
+
+            if pawn_square & 0x88 != 0 || self.get_piece(pawn_square) != (self.side_to_move.opposite() as u8 | PAWN).into() {
+                return Err(PackError::BadEnPassant);
+            }
+
+            codes[pawn_square as usize] = EN_PASSANT_PAWN;
+
+        }
+
+        for castling_right in CASTLING_RIGHTS {
+
+            if self.castling_rights & castling_right == NO_CASTLING_RIGHTS {
+                continue;
+            }
+
+            let rook_square = self.get_castling_rook(castling_right);
+
+            codes[rook_square as usize] = match self.get_piece(rook_square) {
+                WhiteRook if castling_right & (WHITE_KINGSIDE | WHITE_QUEENSIDE) != NO_CASTLING_RIGHTS => WHITE_CASTLING_ROOK,
+                BlackRook if castling_right & (BLACK_KINGSIDE | BLACK_QUEENSIDE) != NO_CASTLING_RIGHTS => BLACK_CASTLING_ROOK,
+                _ => return Err(PackError::BadCastling(castling_right))
+            };
+        }
+
+        let halfmove_clock = u16::try_from(self.halfmove_clock).map_err(|_| PackError::CounterTooLarge)?;
+        let fullmove_number = u16::try_from(self.turns_taken / 2 + 1).map_err(|_| PackError::CounterTooLarge)?;
+
+        let mut bytes = [0; 32];
+        bytes[0..8].copy_from_slice(&occupied.to_le_bytes());
+
+        for (idx, square) in bitboard_squares(occupied).enumerate() {
+            bytes[8 + idx / 2] |= codes[square as usize] << (4 * (idx % 2));
+        }
+
+        bytes[24] = if self.side_to_move == Black {BLACK_TO_MOVE_FLAG} else {0} | if self.chess960 {CHESS960_FLAG} else {0};
+        bytes[25] = Variant::ALL.iter().position(|&variant| variant == self.variant).unwrap() as u8;
+        bytes[26] = self.get_remaining_checks(White) << 4 | self.get_remaining_checks(Black);
+        bytes[28..30].copy_from_slice(&halfmove_clock.to_le_bytes());
+        bytes[30..32].copy_from_slice(&fullmove_number.to_le_bytes());
+
+        Ok(PackedBoard(bytes))
+
+    }
+}
+
+impl PackedBoard {
+
+    pub fn decode(&self) -> Result<Board, PackError> {
+
+        let bytes = &self.0;
+        let occupied = Bitboard::from_le_bytes(bytes[0..8].try_into().unwrap());
+
+        if occupied.count_ones() > 32 {
+            return Err(PackError::TooManyPieces(occupied.count_ones()));
+        }
+
+        let variant = *Variant::ALL.get(bytes[25] as usize).ok_or(PackError::BadVariant(bytes[25]))?;
+        let side_to_move = if bytes[24] & BLACK_TO_MOVE_FLAG != 0 {Black} else {White};
+
+        let mut board = Board::empty();
+        board.variant = variant;
+        board.side_to_move = side_to_move;
+        board.remaining_checks = [(bytes[26] >> 4).min(3), (bytes[26] & 0xF).min(3)];
+
+        let mut castling_rooks = Vec::new();
+
+        for (idx, square) in bitboard_squares(occupied).enumerate() {
+
+            let code = (bytes[8 + idx / 2] >> (4 * (idx % 2))) & 0xF;
+
+            let piece = match code {
+                EN_PASSANT_PAWN => {
+                    if board.en_passant_chance.is_some() {
+                        return Err(PackError::BadPieceCode(code));
+                    }
+                    board.en_passant_chance = Some(side_to_move.offset_rank(square));
+                    (side_to_move.opposite() as u8 | PAWN).into()
+                }
+                WHITE_CASTLING_ROOK => {
+                    castling_rooks.push((White, square));
+                    WhiteRook
+                }
+                BLACK_CASTLING_ROOK => {
+                    castling_rooks.push((Black, square));
+                    BlackRook
+                }
+                0..=11 => code_to_piece(code),
+                _ => return Err(PackError::BadPieceCode(code))
+            };
+
+            board.set_piece(square, piece);
+
+        }
+
+        for colour in [White, Black] {
+            if variant.has_king(colour) && variant != Variant::Atomic && board.get_piece_bitboard((colour as u8 | KING).into()) == 0 {
+                return Err(PackError::MissingKing(colour));
+            }
+        }
+
+        // the rooks are only marked, which side they castle to comes from the king
+        for (colour, rook_square) in castling_rooks {
+
+            if board.get_piece_bitboard((colour as u8 | KING).into()) == 0 {
+                return Err(PackError::MissingKing(colour));
+            }
+
+            let kingside = rook_square % 16 > board.get_king_square(colour) % 16;
+            board.add_castling_right(get_castling_right(colour, kingside), Some(rook_square));
+        }
+
+        let fullmove_number = u16::from_le_bytes([bytes[30], bytes[31]]).max(1) as u32;
+
+        board.chess960 |= bytes[24] & CHESS960_FLAG != 0;
+        board.halfmove_clock = u16::from_le_bytes([bytes[28], bytes[29]]) as u32;
+        board.turns_taken = fullmove_number * 2 - 2 + if side_to_move == White {0} else {1};
+        board.refresh_hash();
+
+        Ok(board)
+
+    }
+}
+
+// a packed position with what was learned about it, for tuning and test sets
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PositionRecord {
+    pub board: PackedBoard,
+    pub score: Option<i32>,
+    pub outcome: Option<Outcome>
+}
+
+impl PositionRecord {
+
+    pub fn new(board: &Board) -> Result<Self, PackError> {
+        Ok(Self {
+            board: board.encode()?,
+            score: None,
+            outcome: None
+        })
+    }
+}
+
+// records are the packed board, a flag byte saying what follows, then the score if there is one
+pub struct PositionWriter<W: Write> {
+    writer: W
+}
+
+impl<W: Write> PositionWriter<W> {
+
+    pub fn new(writer: W) -> Self {
+        Self {writer}
+    }
+
+    pub fn write(&mut self, record: &PositionRecord) -> io::Result<()> {
+
+        let outcome_bits = match record.outcome {
+            None => 0,
+            Some(Outcome::Draw) => 1,
+            Some(Outcome::Win(White)) => 2,
+            Some(Outcome::Win(Black)) => 3
+        };
+
+        let flags = if record.score.is_some() {SCORE_FLAG} else {0} | outcome_bits << OUTCOME_SHIFT;
+
+        self.writer.write_all(&record.board.0)?;
+        self.writer.write_all(&[flags])?;
+
+        if let Some(score) = record.score {
+            self.writer.write_all(&score.to_le_bytes())?;
+        }
+
+        Ok(())
+
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct PositionReader<R: Read> {
+    reader: R
+}
+
+impl<R: Read> PositionReader<R> {
+
+    pub fn new(reader: R) -> Self {
+        Self {reader}
+    }
+
+    // none at a clean end of the stream, an error if it stops partway through a record
+    pub fn read(&mut self) -> io::Result<Option<PositionRecord>> {
+
+        let mut bytes = [0; 33];
+        let mut filled = 0;
+
+        while filled < bytes.len() {
+            match self.reader.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err)
+            }
+        }
+
+        let flags = bytes[32];
+
+        if flags >> (OUTCOME_SHIFT + 2) != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown record flags"));
+        }
+
+        let score = if flags & SCORE_FLAG != 0 {
+            let mut score_bytes = [0; 4];
+            self.reader.read_exact(&mut score_bytes)?;
+            Some(i32::from_le_bytes(score_bytes))
+        }
+        else {
+            None
+        };
+
+        let outcome = match (flags >> OUTCOME_SHIFT) & 0b11 {
+            1 => Some(Outcome::Draw),
+            2 => Some(Outcome::Win(White)),
+            3 => Some(Outcome::Win(Black)),
+            _ => None
+        };
+
+        Ok(Some(PositionRecord {
+            board: PackedBoard(bytes[..32].try_into().unwrap()),
+            score,
+            outcome
+        }))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for PositionReader<R> {
+    type Item = io::Result<PositionRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
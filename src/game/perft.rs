@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::board::*;
+use super::movegen::*;
+use super::r#move::*;
+use crate::hash::HashTable;
+
+// 2^18 buckets of four, 16MB for each thread
+type PerftTable = HashTable<u64, 18, 4>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PerftOptions {
+    pub threads: usize,
+    // reuses counts of positions reached again by zobrist hash, a collision could throw it off
+    pub hashed: bool
+}
+
+impl Default for PerftOptions {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            hashed: false
+        }
+    }
+}
+
+// the same position counts differently at different depths, so the depth goes into the key
+fn get_perft_key(board: &Board, depth: u32) -> u64 {
+    board.hash() ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+fn hashed_perft(board: &mut Board, depth: u32, table: &mut PerftTable) -> u64 {
+
+    // the last ply is just a move count, not worth a lookup
+    if depth <= 1 {
+        return get_num_moves(board, depth);
+    }
+
+    let key = get_perft_key(board, depth);
+
+    if let Some(&mut nodes) = table.get(key) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+
+    for possible_move in &get_possible_moves(board) {
+        board.make_move(possible_move);
+        nodes += hashed_perft(board, depth - 1, table);
+        board.undo_move();
+    }
+
+    table.set(key, nodes);
+    nodes
+
+}
+
+pub fn perft(board: &Board, depth: u32, options: &PerftOptions) -> u64 {
+
+    if depth == 0 {
+        return 1;
+    }
+
+    perft_divide(board, depth, options).iter().map(|(_, nodes)| nodes).sum()
+
+}
+
+// each root move with the leaves under it, in the order they were generated,
+// threads take root moves one at a time until there are none left
+pub fn perft_divide(board: &Board, depth: u32, options: &PerftOptions) -> Vec<(Move, u64)> {
+
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let root_moves = get_possible_moves(board);
+    let next_move = AtomicUsize::new(0);
+
+    let count_root_moves = || {
+
+        let mut board = board.clone();
+        let mut table = options.hashed.then(PerftTable::new);
+        let mut counts = Vec::new();
+
+        loop {
+
+            let idx = next_move.fetch_add(1, Ordering::Relaxed);
+
+            let Some(root_move) = root_moves.get(idx) else {
+                break;
+            };
+
+            board.make_move(root_move);
+
+            let nodes = match &mut table {
+                Some(table) => hashed_perft(&mut board, depth - 1, table),
+                None => get_num_moves(&mut board, depth - 1)
+            };
+
+            board.undo_move();
+            counts.push((idx, nodes));
+
+        }
+
+        counts
+
+    };
+
+    let thread_count = options.threads.clamp(1, root_moves.len().max(1));
+    let mut divide: Vec<(Move, u64)> = root_moves.iter().map(|&root_move| (root_move, 0)).collect();
+
+    let counts: Vec<(usize, u64)> = if thread_count == 1 {
+        count_root_moves()
+    }
+    else {
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..thread_count).map(|_| scope.spawn(count_root_moves)).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    };
+
+    for (idx, nodes) in counts {
+        divide[idx].1 = nodes;
+    }
+
+    divide
+
+}
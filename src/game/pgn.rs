@@ -2,16 +2,40 @@ use std::collections::HashMap;
 
 use super::r#move::*;
 use super::board::*;
+use super::piece::*;
+use super::san::SanError;
+use super::variant::*;
 
 type MoveIndex = std::num::NonZeroUsize;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GameResult {
     Loss,
     Draw,
-    Win
+    Win,
+    // * in a pgn, still going or never finished
+    Unknown
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PgnError {
+    BadSan(SanError),
+    BadFen(FenError),
+    UnknownVariant
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::BadSan(err) => write!(f, "{}", err),
+            Self::BadFen(err) => write!(f, "bad FEN tag: {}", err),
+            Self::UnknownVariant => write!(f, "unknown variant")
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
 #[derive(Debug)]
 pub struct MoveNode {
     pub played_move: Move,
@@ -22,11 +46,25 @@ pub struct MoveNode {
 #[derive(Debug)]
 pub struct Game {
     pub result: GameResult,
+    variant: Variant,
+    // none when the game starts from the variant's usual position
+    start_fen: Option<String>,
     move_list: Vec<MoveNode>
 }
 
 impl Game {
 
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn get_start_board(&self) -> Board {
+        match &self.start_fen {
+            Some(fen) => Board::from_fen_variant(fen, self.variant),
+            None => Board::new_variant(self.variant)
+        }
+    }
+
     pub fn get_root(&self) -> &MoveNode {
         &self.move_list[0]
     }
@@ -39,8 +77,86 @@ impl Game {
         current_move.alternatives.iter().map(|idx| &self.move_list[idx.get()]).collect()
     }
 
-    fn parse_line<'a>(text: &mut impl Iterator<Item = &'a str>, board: &mut Board, move_list: &mut Vec<MoveNode>) -> Option<MoveIndex> {
+    // black's moves only get a number at the start of a line or after a side line
+    fn write_line(&self, board: &mut Board, mut node_idx: usize, pgn: &mut String) {
+
+        let mut show_number = true;
+        let mut num_moves = 0;
+
+        loop {
+
+            let node = &self.move_list[node_idx];
+            let move_number = board.turns_taken / 2 + 1;
 
+            if board.side_to_move == White {
+                *pgn += &format!("{}. ", move_number);
+            }
+            else if show_number {
+                *pgn += &format!("{}... ", move_number);
+            }
+
+            *pgn += &board.san(&node.played_move);
+
+            for alternative_idx in &node.alternatives {
+                *pgn += " (";
+                self.write_line(board, alternative_idx.get(), pgn);
+                *pgn += ")";
+            }
+
+            show_number = !node.alternatives.is_empty();
+            board.make_move(&node.played_move);
+            num_moves += 1;
+
+            match node.main_line {
+                Some(next_idx) => {
+                    node_idx = next_idx.get();
+                    *pgn += " ";
+                }
+                None => break
+            }
+        }
+
+        for _ in 0..num_moves {
+            board.undo_move();
+        }
+    }
+
+    // the moves and the result, after whichever tags it takes to set up anything but the standard start
+    pub fn to_pgn(&self) -> String {
+
+        let mut pgn = String::new();
+
+        if self.variant != Variant::Standard {
+            pgn += &format!("[Variant \"{}\"]\n", self.variant.pgn_name());
+        }
+
+        if let Some(fen) = &self.start_fen {
+            pgn += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen);
+        }
+
+        if !pgn.is_empty() {
+            pgn += "\n";
+        }
+
+        if !self.move_list.is_empty() {
+            self.write_line(&mut self.get_start_board(), 0, &mut pgn);
+            pgn += " ";
+        }
+
+        pgn += match self.result {
+            GameResult::Win => "1-0",
+            GameResult::Loss => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*"
+        };
+
+        pgn
+
+    }
+
+    fn parse_line<'a>(text: &mut impl Iterator<Item = &'a str>, board: &mut Board, move_list: &mut Vec<MoveNode>) -> Result<Option<MoveIndex>, SanError> {
+
+        let mut first_move_idx: Option<usize> = None;
         let mut last_move_idx: Option<usize> = None;
         let mut num_moves = 0;
 
@@ -52,7 +168,7 @@ impl Game {
                 continue;
             }
 
-            if ["1-0", "1/2-1/2", "½-½", "0-1"].contains(&chunk) {
+            if ["1-0", "1/2-1/2", "½-½", "0-1", "*"].contains(&chunk) {
                 break;
             }
 
@@ -60,7 +176,7 @@ impl Game {
 
                 let last_move = board.undo_move().unwrap();
 
-                if let Some(alternative_move) = Self::parse_line(text, board, move_list) {
+                if let Some(alternative_move) = Self::parse_line(text, board, move_list)? {
                     move_list[last_move_idx.unwrap()].alternatives.push(alternative_move);
                 }
 
@@ -69,11 +185,15 @@ impl Game {
 
             }
 
-            if chunk.ends_with(')') {
+            // the last move of a side line can come right before the bracket
+            let ends_line = chunk.ends_with(')');
+            chunk = chunk.trim_end_matches(')');
+
+            if chunk.is_empty() {
                 break;
             }
 
-            let played_move = Move::from_an(&chunk, board).unwrap();
+            let played_move = board.parse_san(chunk)?;
 
             board.make_move(&played_move);
             num_moves += 1;
@@ -90,29 +210,29 @@ impl Game {
                 move_list[idx].main_line = MoveIndex::new(new_move_idx);
             }
 
+            first_move_idx.get_or_insert(new_move_idx);
             last_move_idx = Some(new_move_idx);
 
+            if ends_line {
+                break;
+            }
         }
 
         for _ in 0..num_moves {
             board.undo_move();
         }
 
-        last_move_idx.and_then(MoveIndex::new)
+        Ok(first_move_idx.and_then(MoveIndex::new))
 
     }
     
-    pub fn from_pgn(pgn: String) -> Self {
+    pub fn from_pgn(pgn: String) -> Result<Self, PgnError> {
         
-        let mut lines = pgn.split('\n');
+        let mut lines = pgn.split('\n').peekable();
 
         let mut tags = HashMap::new();
 
-        while let Some(mut line) = lines.next() {
-
-            if !line.starts_with('[') {
-                break;
-            }
+        while let Some(mut line) = lines.next_if(|line| line.starts_with('[')) {
 
             line = line.strip_prefix('[').unwrap();
 
@@ -128,19 +248,36 @@ impl Game {
         let moves_text = lines.fold(String::new(), |acc, line| acc + " " + line);
         let mut moves_split = moves_text.split_inclusive(&[' ', '(', ')']);
 
-        let mut board = Board::default();
+        // chess960 is told apart by its castling rights, not as a variant of its own
+        let variant = match tags.get("Variant") {
+            None | Some(&("Chess960" | "From Position")) => Variant::Standard,
+            Some(name) => Variant::ALL.into_iter()
+                .find(|variant| variant.pgn_name().eq_ignore_ascii_case(name) || variant.name() == *name)
+                .ok_or(PgnError::UnknownVariant)?
+        };
+
+        let mut board = match tags.get("FEN") {
+            Some(fen) => Board::parse_fen_variant(fen, variant).map_err(PgnError::BadFen)?,
+            None => Board::new_variant(variant)
+        };
+
+        let start_fen = tags.contains_key("FEN").then(|| board.get_fen());
+
         let mut move_list = Vec::new();
-        Self::parse_line(&mut moves_split, &mut board, &mut move_list);
+        Self::parse_line(&mut moves_split, &mut board, &mut move_list).map_err(PgnError::BadSan)?;
 
-        let result = match moves_split.next() {
+        let result = match moves_text.split_whitespace().last() {
             Some("1-0") => GameResult::Win,
             Some("0-1") => GameResult::Loss,
-            _ => GameResult::Draw
+            Some("1/2-1/2" | "½-½") => GameResult::Draw,
+            _ => GameResult::Unknown
         };
 
-        Self {
+        Ok(Self {
             result,
+            variant,
+            start_fen,
             move_list
-        }
+        })
     }
 }
@@ -96,6 +96,12 @@ pub enum Piece {
     Empty = 0b1111
 }
 
+// what a crazyhouse pocket can hold, in the order fens list them
+pub const POCKET_PIECES: [Piece; 10] = [
+    WhiteQueen, WhiteRook, WhiteBishop, WhiteKnight, WhitePawn,
+    BlackQueen, BlackRook, BlackBishop, BlackKnight, BlackPawn
+];
+
 impl Piece {
 
     pub fn get_colour(self) -> Colour {
@@ -116,6 +122,13 @@ impl Piece {
 
     pub fn from_char(c: char) -> Self {
         match c {
+            ' ' => Empty,
+            _ => Self::try_from_char(c).unwrap()
+        }
+    }
+
+    pub fn try_from_char(c: char) -> Option<Self> {
+        Some(match c {
             'P' => WhitePawn,
             'N' => WhiteKnight,
             'B' => WhiteBishop,
@@ -128,9 +141,8 @@ impl Piece {
             'r' => BlackRook,
             'q' => BlackQueen,
             'k' => BlackKing,
-            ' ' => Empty,
-            _ => unreachable!()
-        }
+            _ => return None
+        })
     }
 
     pub fn to_char(self) -> char {
@@ -163,6 +175,42 @@ impl Piece {
         }
     }
 
+    pub fn to_figurine(self) -> char {
+        match self {
+            WhitePawn => '♙',
+            WhiteKnight => '♘',
+            WhiteBishop => '♗',
+            WhiteRook => '♖',
+            WhiteQueen => '♕',
+            WhiteKing => '♔',
+            BlackPawn => '♟',
+            BlackKnight => '♞',
+            BlackBishop => '♝',
+            BlackRook => '♜',
+            BlackQueen => '♛',
+            BlackKing => '♚',
+            Empty => ' '
+        }
+    }
+
+    pub fn from_figurine(c: char) -> Option<Self> {
+        Some(match c {
+            '♙' => WhitePawn,
+            '♘' => WhiteKnight,
+            '♗' => WhiteBishop,
+            '♖' => WhiteRook,
+            '♕' => WhiteQueen,
+            '♔' => WhiteKing,
+            '♟' => BlackPawn,
+            '♞' => BlackKnight,
+            '♝' => BlackBishop,
+            '♜' => BlackRook,
+            '♛' => BlackQueen,
+            '♚' => BlackKing,
+            _ => return None
+        })
+    }
+
     pub fn is_pawn(self) -> bool {
         (self as u8) & 0b111 == PAWN
     }
@@ -0,0 +1,191 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::r#move::*;
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const LAST_MOVE_SQUARE: &str = "\x1b[48;5;143m";
+const CHECK_SQUARE: &str = "\x1b[48;5;160m";
+const ATTACKED_SQUARE: &str = "\x1b[48;5;68m";
+const WHITE_PIECE: &str = "\x1b[1;97m";
+const BLACK_PIECE: &str = "\x1b[1;30m";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RenderOptions {
+    pub unicode: bool,
+    pub colour: bool,
+    pub coordinates: bool,
+    pub flipped: bool,
+    pub highlight_last_move: bool,
+    pub highlight_check: bool,
+    pub side_to_move: bool,
+    // squares to mark, bit n is the 0x88 square n like the old eval masks
+    pub attack_mask: u128
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            unicode: false,
+            colour: false,
+            coordinates: true,
+            flipped: false,
+            highlight_last_move: true,
+            highlight_check: true,
+            side_to_move: true,
+            attack_mask: 0
+        }
+    }
+}
+
+impl RenderOptions {
+
+    // for a terminal that can take it
+    pub fn fancy() -> Self {
+        Self {
+            unicode: true,
+            colour: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    None,
+    Attacked,
+    LastMove,
+    Check
+}
+
+fn piece_symbol(piece: Piece, options: &RenderOptions) -> char {
+
+    if !options.unicode {
+        return match piece {
+            Empty => '.',
+            _ => piece.to_char()
+        };
+    }
+
+    // coloured pieces all use the filled shapes so they read the same on either square
+    let piece = if options.colour && piece != Empty {(piece as u8 | BLACK).into()} else {piece};
+
+    match piece {
+        Empty => '·',
+        _ => piece.to_figurine()
+    }
+}
+
+impl Board {
+
+    pub fn render(&self, options: &RenderOptions) -> String {
+
+        let mut last_move_squares = 0u128;
+
+        if options.highlight_last_move {
+            match self.last_move() {
+                Some(last_move) if last_move.move_type == MoveType::Null => {}
+                Some(last_move) => last_move_squares = 1 << last_move.start_square | 1 << last_move.end_square,
+                None => {}
+            }
+        }
+
+        let in_check = self.is_in_check();
+        let check_square = (options.highlight_check && in_check).then(|| self.get_king_square(self.side_to_move));
+
+        // black's view turns both the ranks and the files around
+        let order: Vec<usize> = if options.flipped {(0..8).rev().collect()} else {(0..8).collect()};
+        let mut rendered = String::new();
+
+        for &row in &order {
+
+            if options.coordinates {
+                rendered += &format!("{} ", 8 - row);
+            }
+
+            for &col in &order {
+
+                let square = row_col_to_square(row, col);
+                let piece = self.get_piece(square);
+
+                let highlight = if check_square == Some(square) {
+                    Highlight::Check
+                }
+                else if last_move_squares & 1 << square != 0 {
+                    Highlight::LastMove
+                }
+                else if options.attack_mask & 1 << square != 0 {
+                    Highlight::Attacked
+                }
+                else {
+                    Highlight::None
+                };
+
+                let symbol = if piece == Empty && highlight == Highlight::Attacked && !options.colour {
+                    'x'
+                }
+                else {
+                    piece_symbol(piece, options)
+                };
+
+                if options.colour {
+
+                    let background = match highlight {
+                        Highlight::Check => CHECK_SQUARE,
+                        Highlight::LastMove => LAST_MOVE_SQUARE,
+                        Highlight::Attacked => ATTACKED_SQUARE,
+                        Highlight::None if (row + col) % 2 == 0 => LIGHT_SQUARE,
+                        Highlight::None => DARK_SQUARE
+                    };
+
+                    let foreground = if piece != Empty && piece.is_colour(Black) {BLACK_PIECE} else {WHITE_PIECE};
+
+                    rendered += &format!("{}{} {} {}", background, foreground, symbol, RESET);
+
+                }
+                else {
+
+                    // brackets stand in for the highlight colours
+                    let (left, right) = match highlight {
+                        Highlight::Check => ('(', ')'),
+                        Highlight::LastMove => ('[', ']'),
+                        Highlight::Attacked if piece != Empty => ('<', '>'),
+                        _ => (' ', ' ')
+                    };
+
+                    rendered += &format!("{}{}{}", left, symbol, right);
+
+                }
+            }
+
+            rendered += "\n";
+
+        }
+
+        if options.coordinates {
+
+            rendered += "  ";
+
+            for &col in &order {
+                rendered += &format!(" {} ", (b'a' + col as u8) as char);
+            }
+
+            rendered += "\n";
+
+        }
+
+        if options.side_to_move {
+            rendered += &format!(
+                "{} to move{}\n",
+                if self.side_to_move == White {"White"} else {"Black"},
+                if in_check {", in check"} else {""}
+            );
+        }
+
+        rendered
+
+    }
+}
@@ -0,0 +1,296 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::r#move::*;
+use super::movegen::get_possible_moves;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SanError {
+    Empty,
+    BadPiece(char),
+    BadSquare,
+    BadPromotion(char),
+    MissingPromotion,
+    UnexpectedChar(char),
+    IllegalMove,
+    AmbiguousMove
+}
+
+impl std::fmt::Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no move given"),
+            Self::BadPiece(c) => write!(f, "'{}' is not a piece", c),
+            Self::BadSquare => write!(f, "move has no end square"),
+            Self::BadPromotion(c) => write!(f, "can't promote to '{}'", c),
+            Self::MissingPromotion => write!(f, "promotion doesn't say which piece"),
+            Self::UnexpectedChar(c) => write!(f, "unexpected '{}' in move", c),
+            Self::IllegalMove => write!(f, "no legal move matches"),
+            Self::AmbiguousMove => write!(f, "more than one legal move matches")
+        }
+    }
+}
+
+fn get_piece_code(c: char) -> Option<u8> {
+    match c {
+        'P' => Some(PAWN),
+        'N' => Some(KNIGHT),
+        'B' => Some(BISHOP),
+        'R' => Some(ROOK),
+        'Q' => Some(QUEEN),
+        'K' => Some(KING),
+        _ => None
+    }
+}
+
+fn parse_file(c: char) -> Option<u8> {
+    ('a'..='h').contains(&c).then(|| c as u8 - b'a')
+}
+
+fn parse_rank(c: char) -> Option<u8> {
+    ('1'..='8').contains(&c).then(|| c as u8 - b'1')
+}
+
+pub(super) fn parse_square(file: char, rank: char) -> Option<Square> {
+    Some(row_col_to_square(7 - parse_rank(rank)? as usize, parse_file(file)? as usize))
+}
+
+// only as much of the start square as tells the move apart from the other
+// pieces of the same kind that can get to the same square, the file if that does it
+fn get_disambiguation(san_move: &Move, possible_moves: &[Move]) -> String {
+
+    let mut ambiguous = false;
+    let mut shares_file = false;
+    let mut shares_rank = false;
+
+    for possible_move in possible_moves {
+
+        if possible_move.moved_piece != san_move.moved_piece
+            || possible_move.end_square != san_move.end_square
+            || possible_move.start_square == san_move.start_square
+            || possible_move.is_castle()
+            || possible_move.is_drop() {
+            continue;
+        }
+
+        ambiguous = true;
+        shares_file |= possible_move.start_square & 7 == san_move.start_square & 7;
+        shares_rank |= possible_move.start_square >> 4 == san_move.start_square >> 4;
+
+    }
+
+    let start_square_an = square_to_an(san_move.start_square);
+
+    if !ambiguous {
+        String::new()
+    }
+    else if !shares_file {
+        start_square_an[..1].to_string()
+    }
+    else if !shares_rank {
+        start_square_an[1..].to_string()
+    }
+    else {
+        start_square_an
+    }
+}
+
+// everything but the check suffix
+pub(super) fn get_san_body(san_move: &Move, possible_moves: &[Move]) -> String {
+
+    match san_move.move_type {
+        MoveType::Null => return String::from("--"),
+        MoveType::Castle => return String::from(if san_move.end_square > san_move.start_square {"O-O"} else {"O-O-O"}),
+        MoveType::Drop(piece) => return format!("{}@{}", piece.to_an_char(), square_to_an(san_move.end_square)),
+        _ => {}
+    }
+
+    let is_capture = san_move.is_capture();
+
+    let start = if san_move.moved_piece.is_pawn() {
+        if is_capture {square_to_an(san_move.start_square)[..1].to_string()} else {String::new()}
+    }
+    else {
+        format!("{}{}", san_move.moved_piece.to_an_char(), get_disambiguation(san_move, possible_moves))
+    };
+
+    let promotion = san_move.promotion_piece().map_or(String::new(), |promote_piece| format!("={}", promote_piece.to_an_char()));
+
+    format!("{}{}{}{}", start, if is_capture {"x"} else {""}, square_to_an(san_move.end_square), promotion)
+
+}
+
+impl Board {
+
+    pub fn san(&self, san_move: &Move) -> String {
+        get_san_body(san_move, &get_possible_moves(self)) + self.get_check_suffix(san_move)
+    }
+
+    pub(super) fn get_check_suffix(&self, checking_move: &Move) -> &'static str {
+
+        if !checking_move.gives_check(self) {
+            return "";
+        }
+
+        let mut board = self.clone();
+        board.make_move(checking_move);
+
+        if get_possible_moves(&board).is_empty() {"#"} else {"+"}
+
+    }
+
+    // takes the sloppy forms people and other programs write too, like e8Q, 0-0, exd6e.p. and Ng1f3
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+
+        let mut text = san.trim();
+
+        // check marks, annotations and en passant markers don't change which move it is
+        loop {
+
+            let mut stripped = text.trim_end_matches(['+', '#', '!', '?']).trim_end();
+            stripped = stripped.strip_suffix("e.p.").unwrap_or(stripped).trim_end();
+
+            if stripped == text {
+                break;
+            }
+
+            text = stripped;
+
+        }
+
+        if text.is_empty() {
+            return Err(SanError::Empty);
+        }
+
+        if text == "--" {
+            return Ok(Move::new_null(self));
+        }
+
+        let possible_moves = get_possible_moves(self);
+
+        let castle_kingside = match text.to_ascii_uppercase().replace('0', "O").as_str() {
+            "O-O" => Some(true),
+            "O-O-O" => Some(false),
+            _ => None
+        };
+
+        if let Some(kingside) = castle_kingside {
+            return possible_moves.into_iter().find(|legal_move|
+                legal_move.move_type == MoveType::Castle && (legal_move.end_square > legal_move.start_square) == kingside
+            ).ok_or(SanError::IllegalMove);
+        }
+
+        // crazyhouse drops, the pawn's letter is optional
+        if let Some((piece_text, square_text)) = text.split_once('@') {
+
+            let drop_code = match piece_text.chars().next() {
+                None => PAWN,
+                Some(c) => match get_piece_code(c) {
+                    Some(KING) | None => return Err(SanError::BadPiece(c)),
+                    Some(code) => code
+                }
+            };
+
+            let mut square_chars = square_text.chars();
+
+            let Some(square) = square_chars.next().zip(square_chars.next()).and_then(|(file, rank)| parse_square(file, rank)) else {
+                return Err(SanError::BadSquare);
+            };
+
+            let drop_piece = Piece::from(drop_code | self.side_to_move as u8);
+
+            return possible_moves.into_iter().find(|legal_move|
+                legal_move.move_type == MoveType::Drop(drop_piece) && legal_move.end_square == square
+            ).ok_or(SanError::IllegalMove);
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+
+        // lowercase letters at the front are files, so b is never a bishop
+        let piece_code = match chars[0] {
+            c if c.is_ascii_uppercase() => {
+                chars.remove(0);
+                get_piece_code(c).ok_or(SanError::BadPiece(c))?
+            }
+            _ => PAWN
+        };
+
+        // e8=Q, e8Q, e8(Q) and e8/Q
+        let mut promotion_code = None;
+
+        if chars.last() == Some(&')') {
+            chars.pop();
+        }
+
+        // only a letter after the square is a piece, on its own it's a bad square
+        if let [.., '1'..='8' | '=' | '(' | '/', c] = chars[..] {
+            if c.is_ascii_alphabetic() {
+
+                chars.pop();
+
+                promotion_code = match get_piece_code(c.to_ascii_uppercase()) {
+                    Some(code @ (KNIGHT | BISHOP | ROOK | QUEEN)) => Some(code),
+                    _ => return Err(SanError::BadPromotion(c))
+                };
+
+                if matches!(chars.last(), Some('=' | '(' | '/')) {
+                    chars.pop();
+                }
+            }
+        }
+
+        let (Some(rank), Some(file)) = (chars.pop(), chars.pop()) else {
+            return Err(SanError::BadSquare);
+        };
+
+        let end_square = parse_square(file, rank).ok_or(SanError::BadSquare)?;
+
+        let mut disambiguate_file = None;
+        let mut disambiguate_rank = None;
+
+        for c in chars {
+            match c {
+                'x' | ':' | '-' => {}
+                _ if parse_file(c).is_some() => disambiguate_file = parse_file(c),
+                _ if parse_rank(c).is_some() => disambiguate_rank = parse_rank(c),
+                _ => return Err(SanError::UnexpectedChar(c))
+            }
+        }
+
+        let mut found_move = None;
+
+        for legal_move in possible_moves {
+
+            if legal_move.end_square != end_square
+                || legal_move.moved_piece as u8 & 0b111 != piece_code
+                || legal_move.is_castle()
+                || legal_move.is_drop() {
+                continue;
+            }
+
+            if disambiguate_file.is_some_and(|file| file != legal_move.start_square & 7)
+                || disambiguate_rank.is_some_and(|rank| rank != 7 - (legal_move.start_square >> 4)) {
+                continue;
+            }
+
+            // leaving off the piece to promote to would match all four
+            match (legal_move.move_type, promotion_code) {
+                (MoveType::Promotion(promote_piece), Some(code)) if promote_piece as u8 & 0b111 == code => {}
+                (MoveType::Promotion(_), None) => return Err(SanError::MissingPromotion),
+                (_, None) => {}
+                _ => continue
+            }
+
+            if found_move.is_some() {
+                return Err(SanError::AmbiguousMove);
+            }
+
+            found_move = Some(legal_move);
+
+        }
+
+        found_move.ok_or(SanError::IllegalMove)
+
+    }
+}
@@ -0,0 +1,195 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::r#move::*;
+use super::movegen::get_attackers;
+use super::variant::*;
+
+// the same as basic_eval, a king is never really traded off
+const SEE_VALUES: [i32; 6] = [100, 320, 330, 530, 960, 20000];
+
+pub fn see_value(piece: Piece) -> i32 {
+    match piece {
+        Empty => 0,
+        _ => SEE_VALUES[piece as usize & 0b111]
+    }
+}
+
+// what the move takes off the board, and what it leaves standing on the end square
+fn get_exchange_start(board: &Board, exchange_move: &Move) -> (i32, Piece, Bitboard) {
+
+    let end_board = square_bitboard(exchange_move.end_square);
+    let mut occupied = board.get_occupied_bitboard() | end_board;
+
+    if !matches!(exchange_move.move_type, MoveType::Drop(_)) {
+        occupied &= !square_bitboard(exchange_move.start_square);
+    }
+
+    match exchange_move.move_type {
+        MoveType::EnPassant => {
+            occupied &= !square_bitboard(board.side_to_move.opposite().offset_rank(exchange_move.end_square));
+            (see_value(WhitePawn), exchange_move.moved_piece, occupied)
+        }
+        MoveType::Promotion(promote_piece) => (
+            see_value(exchange_move.replaced_piece) + see_value(promote_piece) - see_value(WhitePawn),
+            promote_piece,
+            occupied
+        ),
+        _ => (see_value(exchange_move.replaced_piece), exchange_move.moved_piece, occupied)
+    }
+}
+
+// the cheapest piece of the colour that can take on the square, slider attacks are
+// worked out on the shrinking occupancy so anything lined up behind shows through
+fn get_least_valuable_attacker(board: &Board, square: Square, colour: Colour, occupied: Bitboard) -> Option<(Square, Piece)> {
+
+    let attackers = get_attackers(board, square, colour, occupied) & occupied;
+
+    if attackers == 0 {
+        return None;
+    }
+
+    for piece_type in [PAWN, KNIGHT, BISHOP, ROOK, QUEEN, KING] {
+
+        let piece = (colour as u8 | piece_type).into();
+        let piece_attackers = attackers & board.get_piece_bitboard(piece);
+
+        if piece_attackers != 0 {
+            return Some((index_to_square(piece_attackers.trailing_zeros() as usize), piece));
+        }
+    }
+
+    None
+
+}
+
+// atomic captures blow up the capturer, so nothing is taken back
+fn get_explosion_value(board: &Board, capture: &Move) -> i32 {
+
+    if !capture.is_capture() {
+        return 0;
+    }
+
+    let colour = board.side_to_move;
+    let mut value = see_value(capture.captured_piece()) - see_value(capture.moved_piece);
+
+    for square in bitboard_squares(get_king_move_board(capture.end_square) & board.get_occupied_bitboard()) {
+
+        let piece = board.get_piece(square);
+
+        if square == capture.start_square || piece.is_pawn() {
+            continue;
+        }
+
+        value += if piece.is_colour(colour) {-see_value(piece)} else {see_value(piece)};
+
+    }
+
+    value
+
+}
+
+// the material the side to move comes out with if both sides keep taking on the
+// end square for as long as it pays, pins are ignored
+pub fn see(board: &Board, exchange_move: &Move) -> i32 {
+
+    if exchange_move.is_castle() || exchange_move.is_null() {
+        return 0;
+    }
+
+    // taking back is an explosion too, which a run of captures doesn't describe
+    if board.variant == Variant::Atomic {
+        return get_explosion_value(board, exchange_move);
+    }
+
+    let square = exchange_move.end_square;
+    let (captured_value, mut piece_on_square, mut occupied) = get_exchange_start(board, exchange_move);
+
+    // seven along each line to the square and the eight knight squares is the most that can take
+    let mut gains = [0; 65];
+    let mut depth = 0;
+    let mut colour = board.side_to_move.opposite();
+
+    gains[0] = captured_value;
+
+    while let Some((attacker_square, attacker)) = get_least_valuable_attacker(board, square, colour, occupied) {
+
+        // the king can only take last
+        if attacker.is_king() && get_least_valuable_attacker(board, square, colour.opposite(), occupied & !square_bitboard(attacker_square)).is_some() {
+            break;
+        }
+
+        // what this side is up by if it takes and nothing comes back
+        depth += 1;
+        gains[depth] = see_value(piece_on_square) - gains[depth - 1];
+
+        occupied &= !square_bitboard(attacker_square);
+        piece_on_square = attacker;
+        colour = colour.opposite();
+
+    }
+
+    // each side only takes when it comes out better than stopping
+    while depth > 0 {
+        gains[depth - 1] = -gains[depth].max(-gains[depth - 1]);
+        depth -= 1;
+    }
+
+    gains[0]
+
+}
+
+// whether see comes to at least the threshold, stopping as soon as the answer is known
+pub fn see_ge(board: &Board, exchange_move: &Move, threshold: i32) -> bool {
+
+    if exchange_move.is_castle() || exchange_move.is_null() {
+        return threshold <= 0;
+    }
+
+    if board.variant == Variant::Atomic {
+        return get_explosion_value(board, exchange_move) >= threshold;
+    }
+
+    let square = exchange_move.end_square;
+    let (captured_value, piece_on_square, mut occupied) = get_exchange_start(board, exchange_move);
+
+    // what's still owed past the threshold, from the point of view of whoever just took
+    let mut balance = captured_value - threshold;
+
+    if balance < 0 {
+        return false;
+    }
+
+    balance -= see_value(piece_on_square);
+
+    if balance >= 0 {
+        return true;
+    }
+
+    let mut colour = board.side_to_move.opposite();
+    let mut side_to_move_wins = true;
+
+    while let Some((attacker_square, attacker)) = get_least_valuable_attacker(board, square, colour, occupied) {
+
+        occupied &= !square_bitboard(attacker_square);
+
+        // taking with the king into a defended square isn't allowed
+        if attacker.is_king() && get_least_valuable_attacker(board, square, colour.opposite(), occupied).is_some() {
+            break;
+        }
+
+        side_to_move_wins = !side_to_move_wins;
+        balance = -balance - 1 - see_value(attacker);
+
+        if balance >= 0 {
+            break;
+        }
+
+        colour = colour.opposite();
+
+    }
+
+    side_to_move_wins
+
+}
@@ -0,0 +1,136 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::movegen::*;
+use super::variant::*;
+
+const WHITE_BACK_RANK: Bitboard = 0xFF00000000000000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PositionProblem {
+    KingCount(Colour, u32),
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+    CastlingWithoutKing(CastlingRights),
+    CastlingWithoutRook(CastlingRights),
+    BadEnPassant(Square),
+    TooManyPawns(Colour),
+    TooManyPieces(Colour)
+}
+
+impl std::fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::KingCount(colour, count) => write!(f, "{:?} has {} kings", colour, count),
+            Self::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {}", square_to_an(*square)),
+            Self::OpponentInCheck => write!(f, "the side not to move is in check"),
+            Self::CastlingWithoutKing(_) => write!(f, "castling right with the king off its back rank"),
+            Self::CastlingWithoutRook(_) => write!(f, "castling right without a rook to castle with"),
+            Self::BadEnPassant(square) => write!(f, "no pawn could have just skipped over {}", square_to_an(*square)),
+            Self::TooManyPawns(colour) => write!(f, "{:?} has more than 8 pawns", colour),
+            Self::TooManyPieces(colour) => write!(f, "{:?} has more pieces than promotions could give", colour)
+        }
+    }
+}
+
+impl Board {
+
+    // everything that can't come up in a real game, empty if the position is fine
+    pub fn validate(&self) -> Vec<PositionProblem> {
+
+        let mut problems = Vec::new();
+
+        let mut kings_ok = true;
+
+        for colour in [White, Black] {
+
+            let king_count = self.get_piece_bitboard((colour as u8 | KING).into()).count_ones();
+
+            if king_count != self.variant.has_king(colour) as u32 {
+                problems.push(PositionProblem::KingCount(colour, king_count));
+                kings_ok = false;
+            }
+        }
+
+        // the horde starts with pawns on its own back rank
+        let pawns = if self.variant == Variant::Horde {
+            self.get_piece_bitboard(WhitePawn) & !WHITE_BACK_RANK | self.get_piece_bitboard(BlackPawn)
+        }
+        else {
+            self.get_piece_bitboard(WhitePawn) | self.get_piece_bitboard(BlackPawn)
+        };
+
+        for square in bitboard_squares(pawns & BACK_RANKS) {
+            problems.push(PositionProblem::PawnOnBackRank(square));
+        }
+
+        // the king squares mean nothing without exactly one king each
+        if kings_ok && is_in_check(self, self.side_to_move.opposite()) {
+            problems.push(PositionProblem::OpponentInCheck);
+        }
+
+        for colour in [White, Black] {
+            for kingside in [true, false] {
+
+                let castling_right = get_castling_right(colour, kingside);
+
+                if self.castling_rights & castling_right == NO_CASTLING_RIGHTS {
+                    continue;
+                }
+
+                let king_square = self.get_king_square(colour);
+                let rook_square = self.get_castling_rook(castling_right);
+
+                if !kings_ok || king_square / 16 != rook_square / 16 {
+                    problems.push(PositionProblem::CastlingWithoutKing(castling_right));
+                }
+                else if self.get_piece(rook_square) != (colour as u8 | ROOK).into() || (rook_square > king_square) != kingside {
+                    problems.push(PositionProblem::CastlingWithoutRook(castling_right));
+                }
+            }
+        }
+
+        if let Some(en_passant_square) = self.en_passant_chance {
+
+            let en_passant_row = match self.side_to_move {
+                White => 2,
+                Black => 5
+            };
+
+            // the pawn moved from behind the square to in front of it
+            let opp_pawn = (self.side_to_move.opposite() as u8 | PAWN).into();
+            let start_square = self.side_to_move.offset_rank(en_passant_square);
+            let end_square = self.side_to_move.opposite().offset_rank(en_passant_square);
+
+            if en_passant_square / 16 != en_passant_row || self.get_piece(en_passant_square) != Empty ||
+            self.get_piece(start_square) != Empty || self.get_piece(end_square) != opp_pawn {
+                problems.push(PositionProblem::BadEnPassant(en_passant_square));
+            }
+        }
+
+        for colour in [White, Black] {
+
+            // crazyhouse captures change sides, so anything goes
+            if !self.variant.has_king(colour) || self.variant == Variant::Crazyhouse {
+                continue;
+            }
+
+            let [pawns, knights, bishops, rooks, queens, _] = self.get_piece_counts(colour);
+
+            if pawns > 8 {
+                problems.push(PositionProblem::TooManyPawns(colour));
+                continue;
+            }
+
+            let promoted = knights.saturating_sub(2) + bishops.saturating_sub(2) + rooks.saturating_sub(2) + queens.saturating_sub(1);
+
+            if self.get_colour_bitboard(colour).count_ones() > 16 || promoted > 8 - pawns {
+                problems.push(PositionProblem::TooManyPieces(colour));
+            }
+        }
+
+        problems
+
+    }
+}
@@ -0,0 +1,118 @@
+#![allow(non_upper_case_globals)]
+use super::chess_util::*;
+use super::piece::*;
+use super::board::*;
+use super::r#move::*;
+
+const HILL_SQUARES: Bitboard = 0x0000001818000000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    KingOfTheHill,
+    ThreeCheck,
+    Atomic,
+    Horde,
+    Crazyhouse
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Win(Colour),
+    Draw
+}
+
+impl Variant {
+
+    pub const ALL: [Variant; 6] = [Variant::Standard, Variant::KingOfTheHill, Variant::ThreeCheck, Variant::Atomic, Variant::Horde, Variant::Crazyhouse];
+
+    // the names UCI_Variant uses
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "chess",
+            Self::KingOfTheHill => "kingofthehill",
+            Self::ThreeCheck => "3check",
+            Self::Atomic => "atomic",
+            Self::Horde => "horde",
+            Self::Crazyhouse => "crazyhouse"
+        }
+    }
+
+    // what a pgn's Variant tag calls it
+    pub fn pgn_name(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::ThreeCheck => "Three-check",
+            Self::Atomic => "Atomic",
+            Self::Horde => "Horde",
+            Self::Crazyhouse => "Crazyhouse"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|variant| variant.name() == name)
+    }
+
+    pub fn get_start_fen(self) -> &'static str {
+        match self {
+            Self::ThreeCheck => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1",
+            Self::Horde => "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+            Self::Crazyhouse => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            _ => "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        }
+    }
+
+    // horde's white side has no king
+    pub fn has_king(self, colour: Colour) -> bool {
+        !(self == Self::Horde && colour == White)
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Board {
+
+    pub fn new_variant(variant: Variant) -> Self {
+        Self::from_fen_variant(variant.get_start_fen(), variant)
+    }
+
+    // wins that a variant rule ended the game with, checkmate isn't one of them
+    pub fn get_variant_win(&self) -> Option<Colour> {
+        match self.variant {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::KingOfTheHill => [White, Black].into_iter().find(|&colour|
+                self.get_piece_bitboard((colour as u8 | KING).into()) & HILL_SQUARES != 0
+            ),
+            Variant::ThreeCheck => [White, Black].into_iter().find(|&colour| self.get_remaining_checks(colour) == 0),
+            Variant::Atomic => [White, Black].into_iter().find(|&colour|
+                self.get_piece_bitboard((colour.opposite() as u8 | KING).into()) == 0
+            ),
+            Variant::Horde => (self.get_colour_bitboard(White) == 0).then_some(Black)
+        }
+    }
+
+    // none while the game goes on, the moves are the side to move's legal ones
+    pub fn get_outcome(&self, possible_moves: &[Move]) -> Option<Outcome> {
+
+        if let Some(winner) = self.get_variant_win() {
+            return Some(Outcome::Win(winner));
+        }
+
+        if !possible_moves.is_empty() {
+            return None;
+        }
+
+        Some(if self.is_in_check() {
+            Outcome::Win(self.side_to_move.opposite())
+        }
+        else {
+            Outcome::Draw
+        })
+    }
+}
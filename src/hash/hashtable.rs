@@ -25,6 +25,12 @@ pub struct HashTable<T: Default + Copy + Sized, const COMP_BITS: usize, const BU
     table: Box<[Bucket<T, BUCKET_SIZE>]>
 }
 
+impl<T: Default + Copy + Sized, const COMP_BITS: usize, const BUCKET_SIZE: usize> Default for HashTable<T, COMP_BITS, BUCKET_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Default + Copy + Sized, const COMP_BITS: usize, const BUCKET_SIZE: usize> HashTable<T, COMP_BITS, BUCKET_SIZE> {
 
     // using hash 0 to mean no entry
@@ -57,10 +63,17 @@ impl<T: Default + Copy + Sized, const COMP_BITS: usize, const BUCKET_SIZE: usize
 
         let bucket = self.get_bucket(hash);
 
-        bucket.hashes[0] = hash;
+        if let Some(idx) = bucket.hashes.iter().position(|&saved_hash| saved_hash == hash) {
+            bucket.values[idx] = val;
+            return;
+        }
+
+        // hashes have to move with their values or the older entries get mixed up
+        bucket.hashes.rotate_right(1);
         bucket.values.rotate_right(1);
+        bucket.hashes[0] = hash;
         bucket.values[0] = val;
-        
+
     }
 
     pub fn clear(&mut self) {
@@ -1,13 +1,15 @@
+use std::sync::OnceLock;
+
 use crate::game::{self, CastlingRights, Square};
 
-struct RNG {
+struct Rng {
     seed: u128,
 }
 
-impl RNG {
+impl Rng {
 
-    pub fn new() -> RNG {
-        RNG {seed: 0x24707328e71eb479975be17e82370742}
+    pub fn new() -> Rng {
+        Rng {seed: 0x24707328e71eb479975be17e82370742}
     }
 
     pub fn get_rand(&mut self) -> u64 {
@@ -16,18 +18,28 @@ impl RNG {
     }
 }
 
+static SHARED_ZOBRIST: OnceLock<Zobrist> = OnceLock::new();
+
 pub struct Zobrist {
     piece_positions: [[u64; 144]; 16],
     side_to_move_is_black: u64,
     castling_rights: [u64; 16],
-    en_passant_file: [u64; 8]
+    en_passant_file: [u64; 8],
+    remaining_checks: [[u64; 4]; 2],
+    pocket_counts: [[u64; 17]; 16]
+}
+
+impl Default for Zobrist {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Zobrist {
 
     pub fn new() -> Zobrist {
 
-        let mut rng = RNG::new();
+        let mut rng = Rng::new();
 
         Zobrist {
             piece_positions: array_init::array_init(|arr| 
@@ -35,10 +47,21 @@ impl Zobrist {
                 else {array_init::array_init(|_| 0)}),
             side_to_move_is_black: rng.get_rand(), 
             castling_rights: array_init::array_init(|_| rng.get_rand()),
-            en_passant_file: array_init::array_init(|_| rng.get_rand())
+            en_passant_file: array_init::array_init(|_| rng.get_rand()),
+            // three left is the usual state, so it hashes like a normal game
+            remaining_checks: array_init::array_init(|_|
+                array_init::array_init(|checks| if checks != 3 {rng.get_rand()} else {0})),
+            // same for an empty crazyhouse pocket
+            pocket_counts: array_init::array_init(|_|
+                array_init::array_init(|count| if count != 0 {rng.get_rand()} else {0}))
         }
     }
 
+    // the keys are seeded the same way every time, so one set can serve every board
+    pub fn shared() -> &'static Zobrist {
+        SHARED_ZOBRIST.get_or_init(Zobrist::new)
+    }
+
     pub fn get_board_hash(&self, board: &game::Board) -> u64 {
 
         let mut hash = 0u64;
@@ -51,67 +74,46 @@ impl Zobrist {
             hash ^= self.side_to_move_is_black;
         }
 
-        hash ^= self.castling_rights[board.castling_rights as usize];
+        hash ^= self.get_castling_hash(board.castling_rights);
+        hash ^= self.get_en_passant_hash(board.en_passant_chance);
 
-        hash ^= match board.en_passant_chance {
-            Some(square) => self.en_passant_file[square as usize % 8],
-            None => 0
-        };
+        for colour in [game::White, game::Black] {
+            hash ^= self.get_remaining_checks_hash(colour, board.get_remaining_checks(colour));
+        }
+
+        for piece in game::POCKET_PIECES {
+            hash ^= self.get_pocket_hash(piece, board.get_pocket_count(piece));
+        }
 
         hash
 
     }
 
-    pub fn update_hash(&self, mut hash: u64, move_made: &game::Move, old_en_passant_chance: Option<u8>, old_castling_rights: game::CastlingRights, new_castling_rights: CastlingRights) -> u64 {
-
-        let moved_piece_hashes = self.piece_positions[move_made.moved_piece as usize];
-        
-        let start = move_made.start_square as usize;
-        let end = move_made.end_square as usize;
+    pub fn get_piece_hash(&self, piece: game::Piece, square: Square) -> u64 {
+        self.piece_positions[piece as usize][square as usize]
+    }
 
-        hash ^= moved_piece_hashes[start];
-        hash ^= moved_piece_hashes[end];
+    pub fn get_side_to_move_hash(&self) -> u64 {
+        self.side_to_move_is_black
+    }
 
-        if move_made.replaced_piece != game::Empty {
-            hash ^= self.piece_positions[move_made.replaced_piece as usize][end];
-        }
+    pub fn get_castling_hash(&self, castling_rights: CastlingRights) -> u64 {
+        self.castling_rights[castling_rights as usize]
+    }
 
-        let old_en_passant_hash = match old_en_passant_chance {
+    pub fn get_en_passant_hash(&self, en_passant_chance: Option<Square>) -> u64 {
+        match en_passant_chance {
             Some(square) => self.en_passant_file[square as usize % 8],
             None => 0
-        };
-        hash ^= old_en_passant_hash;
-
-        hash ^= match move_made.move_type {
-            game::MoveType::EnPassant => 
-                self.piece_positions[game::BlackPawn as usize][move_made.moved_piece.get_colour().opposite().offset_rank(end as Square) as usize],
-            game::MoveType::PawnDouble =>
-                old_en_passant_hash ^
-                self.en_passant_file[end % 8],
-            game::MoveType::Promotion(piece) =>
-                moved_piece_hashes[end] ^ 
-                self.piece_positions[piece as usize][end],
-            game::MoveType::Castle => {
-                
-                let rook = self.piece_positions[(move_made.moved_piece.get_colour() as u8 | game::ROOK) as usize];
-                let (rook_start_square, rook_end_square) = if end % 8 < 4 {
-                    (end - 2, end + 1)
-                } else {
-                    (end + 1, end - 1)
-                };
-                
-                rook[rook_start_square] ^ rook[rook_end_square]
-                
-            }
-            game::MoveType::Normal => 0,
-        };
-
-        hash ^= self.castling_rights[old_castling_rights as usize];
-        hash ^= self.castling_rights[new_castling_rights as usize];
-
-        hash ^= self.side_to_move_is_black;
+        }
+    }
 
-        hash
+    pub fn get_remaining_checks_hash(&self, colour: game::Colour, remaining_checks: u8) -> u64 {
+        self.remaining_checks[colour as usize >> 3][remaining_checks as usize]
+    }
 
+    // a real game can't have more than 16 of a piece in hand, made up positions just share a key
+    pub fn get_pocket_hash(&self, piece: game::Piece, count: u8) -> u64 {
+        self.pocket_counts[piece as usize][count.min(16) as usize]
     }
-}
\ No newline at end of file
+}
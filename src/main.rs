@@ -1,7 +1,6 @@
 #![allow(clippy::needless_return)]
-mod game;
-mod player;
-mod hash;
+use chess::game;
+use chess::player;
 
 use std::io::stdin;
 use std::time;
@@ -15,7 +14,7 @@ fn main() {
 
     match split.next().unwrap() {
         "uci" => uci(),
-        "perft" => perft(String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), split.next().unwrap().parse::<usize>().unwrap() as u32),
+        "perft" => perft(&split.collect::<Vec<&str>>()),
         _ => internal_sim()
     }
 }
@@ -31,8 +30,13 @@ fn uci() {
 
     println!("id name Chess");
     println!("id author DecklynKern");
+    println!("option name UCI_Chess960 type check default false");
+    println!("option name UCI_Variant type combo default chess{}", game::Variant::ALL.iter().map(|variant| format!(" var {}", variant)).collect::<String>());
+    println!("uciok");
 
     let mut board = game::Board::default();
+    let mut chess960 = false;
+    let mut variant = game::Variant::Standard;
 
     let mut player: Box<dyn player::Player>;
     player = Box::new(player::AlphaBetaPlayer::new(6, &player::advanced_eval));
@@ -45,39 +49,86 @@ fn uci() {
         let mut split = line.trim().split(' ');
 
         match split.next().unwrap().trim() {
-            "d" => print_board(&board),
+            "d" => {
+                print!("{}", board.render(&game::RenderOptions::default()));
+                println!("Fen: {}", board.get_fen());
+            }
             "isready" => println!("readyok"),
-            "setoption" => {}, // can change
+            "setoption" => {
+
+                let args = split.map(str::trim).collect::<Vec<&str>>();
+
+                match args.as_slice() {
+                    ["name", "UCI_Chess960", "value", value] => chess960 = *value == "true",
+                    ["name", "UCI_Variant", "value", value] => match game::Variant::from_name(value) {
+                        Some(new_variant) => variant = new_variant,
+                        None => println!("info string unknown variant: {}", value)
+                    },
+                    _ => {}
+                }
+            },
             "register" => {}, // ?
             "ucinewgame" => {}, //?
             "eval" => {
                 println!("Eval: {}", (player.get_raw_eval(&board) as f32) / 100.0)
             }
             "position" => {
-                let arg2 = split.next().unwrap().trim();
-                if arg2 == "startpos" {
-                    board = game::Board::default();                   
-                    match split.next() {
-                        Some(arg) => assert_eq!(arg.trim(), "moves"),
-                        None => continue
-                    }
-                    for move_to_play in split {
 
-                        let trimmed = move_to_play.trim();
+                let arg2 = split.next().unwrap_or("").trim();
 
-                        // remove magic numbers potentially
-                        board.make_move(&game::Move::from_long_an(trimmed, &board));
-                        
-                    }
+                board = if arg2 == "startpos" {
+                    game::Board::new_variant(variant)
                 }
                 else {
-                    board = game::Board::from_fen(split.collect::<Vec<&str>>().join(" "));
+
+                    let fen = split.by_ref()
+                        .take_while(|arg| arg.trim() != "moves")
+                        .collect::<Vec<&str>>()
+                        .join(" ");
+
+                    match game::Board::parse_fen_variant_strict(&fen, variant) {
+                        Ok(fen_board) => fen_board,
+                        Err(err) => {
+                            println!("info string invalid fen: {}", err);
+                            continue;
+                        }
+                    }
+                };
+
+                // a standard start position can still be played as chess960
+                board.chess960 |= chess960;
+
+                // take_while already ate "moves" for a fen
+                if arg2 == "startpos" && split.next().map(str::trim) != Some("moves") {
+                    continue;
+                }
+
+                // the moves after a bad one would be played from the wrong position, so they're dropped too
+                for move_to_play in split.map(str::trim).filter(|text| !text.is_empty()) {
+                    match board.parse_uci_move(move_to_play) {
+                        Ok(valid_move) => board.make_move(&valid_move),
+                        Err(err) => {
+                            println!("info string bad move {}: {}", move_to_play, err);
+                            break;
+                        }
+                    }
                 }
             },
+            "go" if line.split_whitespace().nth(1) == Some("perft") => {
+
+                let args = line.split_whitespace().skip(2).collect::<Vec<&str>>();
+
+                match args.first().map(|depth| depth.parse::<u32>()) {
+                    Some(Ok(depth)) => {
+                        print_perft(&board, depth, &parse_perft_options(&args[1..]));
+                    }
+                    _ => println!("info string go perft needs a depth")
+                }
+            }
             "go" => {
                 let possible_moves = game::get_possible_moves(&board);
                 if let Some(valid_move) = player.get_move(&mut board, &possible_moves) {
-                    let move_text = valid_move.as_long_an();
+                    let move_text = valid_move.as_uci(board.chess960);
                     println!("bestmove {}", move_text);
                 }
                 else {
@@ -95,39 +146,97 @@ fn uci() {
     }
 }
 
-fn perft(fen: String, depth: u32) {
- 
-    let start_time = time::Instant::now();
-
-    let mut board = game::Board::from_fen(fen);
+// threads <n> and hash can follow the depth of either perft
+fn parse_perft_options(args: &[&str]) -> game::PerftOptions {
 
-    let mut total_moves = 0;
+    let mut options = game::PerftOptions::default();
+    let mut args = args.iter();
 
-    for mv in game::get_possible_moves(&board) {
-        board.make_move(&mv);
-        let next_moves = game::get_num_moves(&mut board, depth - 1);
-        total_moves += next_moves;
-        println!("{}: {}", mv.as_long_an(), next_moves);
-        board.undo_move();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "threads" => options.threads = args.next().and_then(|threads| threads.parse().ok()).unwrap_or(1),
+            "hash" => options.hashed = true,
+            _ => {}
+        }
     }
 
-    let end_time = time::Instant::now();
+    options
+
+}
+
+// laid out like other engines' go perft so the divides can be diffed
+fn print_perft(board: &game::Board, depth: u32, options: &game::PerftOptions) -> u64 {
+
+    let divide = game::perft_divide(board, depth, options);
+
+    for (root_move, nodes) in &divide {
+        println!("{}: {}", root_move.as_uci(board.chess960), nodes);
+    }
 
-    let diff = (end_time - start_time).as_millis() as u32;
+    let total_nodes = if depth == 0 {1} else {divide.iter().map(|(_, nodes)| nodes).sum()};
 
     println!();
-    println!("Total time : {}ms", diff);
-    println!("Total moves: {}", total_moves);
+    println!("Nodes searched: {}", total_nodes);
+
+    total_nodes
 
 }
 
-fn print_board(board: &game::Board) {
-    for row in 0..8 {
-        for col in 0..8 {
-            print!("{} ", board.get_piece_rc(row, col).to_char());
+// perft <depth> [fen <fen>] [variant <name>] [threads <n>] [hash]
+fn perft(args: &[&str]) {
+
+    let Some(Ok(depth)) = args.first().map(|depth| depth.parse::<u32>()) else {
+        println!("usage: perft <depth> [fen <fen>] [variant <name>] [threads <n>] [hash]");
+        return;
+    };
+
+    let mut fen = Vec::new();
+    let mut variant = game::Variant::Standard;
+    let mut idx = 1;
+
+    while idx < args.len() {
+        match args[idx] {
+            "fen" => {
+                while idx + 1 < args.len() && !matches!(args[idx + 1], "variant" | "threads" | "hash") {
+                    idx += 1;
+                    fen.push(args[idx]);
+                }
+            }
+            "variant" => {
+                idx += 1;
+                match args.get(idx).and_then(|name| game::Variant::from_name(name)) {
+                    Some(new_variant) => variant = new_variant,
+                    None => {
+                        println!("unknown variant: {}", args.get(idx).unwrap_or(&""));
+                        return;
+                    }
+                }
+            }
+            _ => {}
         }
-        println!()
+        idx += 1;
     }
+
+    let board = if fen.is_empty() {
+        game::Board::new_variant(variant)
+    }
+    else {
+        match game::Board::parse_fen_variant_strict(&fen.join(" "), variant) {
+            Ok(board) => board,
+            Err(err) => {
+                println!("invalid fen: {}", err);
+                return;
+            }
+        }
+    };
+
+    let start_time = time::Instant::now();
+    let total_nodes = print_perft(&board, depth, &parse_perft_options(&args[1..]));
+    let time_taken = start_time.elapsed();
+
+    println!("Time: {}ms", time_taken.as_millis());
+    println!("Nodes/second: {}", (total_nodes as f64 / time_taken.as_secs_f64()) as u64);
+
 }
 
 fn internal_sim() {
@@ -165,7 +274,7 @@ fn internal_sim() {
 
     loop {
 
-        print_board(&board);
+        print!("{}", board.render(&game::RenderOptions::fancy()));
         
         line = get_line();     
 
@@ -189,7 +298,7 @@ fn internal_sim() {
 
         match move_to_make {
             Some(valid_move) => {
-                println!("{} is played.\n", valid_move.as_an(&possible_moves));
+                println!("{} is played.\n", board.san(valid_move));
                 board.make_move(valid_move);
             },
             None => {println!("game over")}
@@ -2,30 +2,9 @@ use crate::player::*;
 use crate::game;
 use crate::hash;
 
-fn basic_move_ordering(moves: Vec<game::Move>) -> Vec<game::Move> {
-
-    let mut sorted_moves = Vec::new();
-
-    for possible_move in &moves {
-        if possible_move.replaced_piece != game::Empty {
-            sorted_moves.push(*possible_move);
-        }
-    }
-
-    for possible_move in &moves {
-        if possible_move.replaced_piece == game::Empty {
-            sorted_moves.push(*possible_move);
-        }
-    }
-
-    return sorted_moves;
-
-}
-
 pub struct AlphaBetaPlayer {
     depth: u32,
     score_board: BoardScore,
-    zobrist_hasher: hash::Zobrist,
     transposition_table: hash::HashTable<i32, 20, 4>,
     nodes_searched: u32
 }
@@ -36,61 +15,45 @@ impl AlphaBetaPlayer {
         Self{
             depth,
             score_board,
-            zobrist_hasher: hash::Zobrist::new(),
             transposition_table: hash::HashTable::new(),
             nodes_searched: 0
         }
     }
 
-    fn find_board_score(&mut self, board: &mut game::Board, depth: u32, mut alpha: i32, beta: i32, board_hash: u64) -> (i32, Option<game::Move>) {
+    // the table holds the score of the move that reached a position, keyed by that position
+    fn find_board_score(&mut self, board: &mut game::Board, depth: u32, mut alpha: i32, beta: i32) -> (i32, Option<game::Move>) {
 
         self.nodes_searched += 1;
 
-        let mut score: i32;
+        // a won variant game has nothing left to evaluate
+        if let Some(winner) = board.get_variant_win() {
+            return (if winner == board.side_to_move {WIN_SCORE} else {LOSE_SCORE}, None);
+        }
 
         if depth == 0 {
-            score = (self.score_board)(board);
-            self.transposition_table.set(board_hash, score);
-            return (score, None);
+            return ((self.score_board)(board), None);
         }
 
-        score = MIN_SCORE;
-
-        let possible_moves = game::get_possible_moves(board);
-
-        if possible_moves.is_empty() {
-            
-            if game::get_position_info(board, board.side_to_move).king_attacker_count == 0 {
-                return (0, None);
-            }
-            
-            return (LOSE_SCORE, None);
-
-        }
+        let mut score = MIN_SCORE;
 
+        let mut move_picker = MovePicker::new(board, None);
         let mut best_move = None;
 
-        for possible_move in basic_move_ordering(possible_moves) {
-
-            let old_en_passant_chance = board.en_passant_chance;
-            let old_castling_rights = board.castling_rights;
+        while let Some(possible_move) = move_picker.next(board) {
 
             board.make_move(&possible_move);
 
-            let new_hash = self.zobrist_hasher.update_hash(
-                board_hash,
-                &possible_move,
-                old_en_passant_chance,
-                old_castling_rights,
-                board.castling_rights
-            );
-
-            let move_score = match self.transposition_table.get(new_hash) {
-                Some(&mut cached_score) => cached_score,
-                _ => {
-                    let move_score = -self.find_board_score(board, depth - 1, -beta, -alpha, board_hash).0;
-                    self.transposition_table.set(board_hash, move_score);
-                    move_score
+            let move_score = if board.is_repetition(2) || board.is_draw_by_fifty_moves() {
+                0
+            }
+            else {
+                match self.transposition_table.get(board.hash()) {
+                    Some(&mut cached_score) => cached_score,
+                    _ => {
+                        let move_score = -self.find_board_score(board, depth - 1, -beta, -alpha).0;
+                        self.transposition_table.set(board.hash(), move_score);
+                        move_score
+                    }
                 }
             };
 
@@ -108,6 +71,14 @@ impl AlphaBetaPlayer {
             }
         }
 
+        // having no moves is a loss unless it's stalemate
+        if move_picker.moves_picked() == 0 {
+            return match board.get_outcome(&[]) {
+                Some(game::Outcome::Win(_)) => (LOSE_SCORE, None),
+                _ => (0, None)
+            };
+        }
+
         return (score - score.signum(), best_move);
 
     }
@@ -124,9 +95,7 @@ impl Player for AlphaBetaPlayer {
         self.transposition_table.clear();
         self.nodes_searched = 0;
 
-        let board_hash = self.zobrist_hasher.get_board_hash(board);
-
-        let (eval, best_move) = self.find_board_score(board, self.depth, MIN_SCORE, MAX_SCORE, board_hash);
+        let (_eval, best_move) = self.find_board_score(board, self.depth, MIN_SCORE, MAX_SCORE);
 
         // println!("nodes searched: {}", self.nodes_searched);
         // println!("eval: {}", eval as f64 / 100.0);
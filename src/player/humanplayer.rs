@@ -8,11 +8,11 @@ pub struct HumanPlayer {
 
 impl player::Player for HumanPlayer {
 
-    fn get_raw_eval(&mut self, board: &game::Board) -> i32 {
+    fn get_raw_eval(&mut self, _board: &game::Board) -> i32 {
         0
     }
 
-    fn get_move<'a>(&mut self, _board: &mut game::Board, possible_moves: &'a [game::Move]) -> Option<&'a game::Move> {
+    fn get_move<'a>(&mut self, board: &mut game::Board, possible_moves: &'a [game::Move]) -> Option<&'a game::Move> {
 
         if possible_moves.is_empty() {
             return None;
@@ -24,7 +24,7 @@ impl player::Player for HumanPlayer {
         println!("Possible moves: ");
 
         for possible_move in possible_moves {
-            print!("{}, ", possible_move.as_an(possible_moves));
+            print!("{}, ", board.san(possible_move));
         }
         println!();
         
@@ -35,16 +35,16 @@ impl player::Player for HumanPlayer {
             line = String::new();
             stdin.read_line(&mut line).unwrap();
             
-            line = line.trim().to_string();
-            
-            for possible_move in possible_moves {
-                if line == possible_move.as_an(possible_moves) {
-                    return Some(possible_move);
+            match board.parse_san(&line) {
+                Ok(chosen_move) => {
+                    if let Some(possible_move) = possible_moves.iter().find(|possible_move| possible_move.pack() == chosen_move.pack()) {
+                        return Some(possible_move);
+                    }
+                    println!("Invalid move.")
                 }
+                Err(error) => println!("Invalid move: {}.", error)
             }
 
-            println!("Invalid move.")
-
         }
     }
 }
\ No newline at end of file
@@ -7,9 +7,8 @@ use std::time;
 pub struct IterativeDeepening {
     max_time_millis: u128,
     score_board: BoardScore,
-    zobrist_hasher: hash::Zobrist,
     transposition_table: hash::HashTable<i32, 20, 4>,
-    pv_table: hash::HashTable<game::Move, 20, 4>,
+    pv_table: hash::HashTable<game::PackedMove, 20, 4>,
     nodes_searched: u32
 }
 
@@ -19,83 +18,50 @@ impl IterativeDeepening {
         Self{
             max_time_millis: approx_time_millis,
             score_board,
-            zobrist_hasher: hash::Zobrist::new(),
             transposition_table: hash::HashTable::new(),
             pv_table: hash::HashTable::new(),
             nodes_searched: 0
         }
     }
 
-    fn find_board_score(&mut self, board: &mut game::Board, depth: u32, mut alpha: i32, beta: i32, board_hash: u64) -> (i32, Option<game::Move>) {
+    // the table holds the score of the move that reached a position, keyed by that position
+    fn find_board_score(&mut self, board: &mut game::Board, depth: u32, mut alpha: i32, beta: i32) -> (i32, Option<game::Move>) {
 
         self.nodes_searched += 1;
 
-        let mut score: i32;
-
-        if depth == 0 {
-            score = (self.score_board)(board);
-            self.transposition_table.set(board_hash, score);
-            return (score, None);
+        // a won variant game has nothing left to evaluate
+        if let Some(winner) = board.get_variant_win() {
+            return (if winner == board.side_to_move {WIN_SCORE} else {LOSE_SCORE}, None);
         }
 
-        score = MIN_SCORE;
-
-        let mut possible_moves = game::get_possible_moves(board);
-
-        if possible_moves.is_empty() { // could also hash these i guess
-            
-            if game::get_position_info(board, board.side_to_move).king_attacker_count == 0 {
-                self.transposition_table.set(board_hash, 0);
-                return (0, None);
-            }
-            
-            self.transposition_table.set(board_hash, LOSE_SCORE);
-            return (LOSE_SCORE, None);
-
+        if depth == 0 {
+            return ((self.score_board)(board), None);
         }
 
-        if let Some(pv_move) = self.pv_table.get(board_hash) {
-
-            for (idx, possible_move) in possible_moves.iter().enumerate() {
-
-                if possible_move.start_square == pv_move.start_square && 
-                possible_move.end_square == pv_move.end_square {
-                    possible_moves.swap(0, idx);
-                    break;
-                }
-            }
-        }
+        let mut score = MIN_SCORE;
 
+        let pv_move = self.pv_table.get(board.hash()).copied();
+        let mut move_picker = MovePicker::new(board, pv_move);
         let mut best_move = None;
 
-        for possible_move in possible_moves {
-
-            let old_en_passant_chance = board.en_passant_chance;
-            let old_castling_rights = board.castling_rights;
+        while let Some(possible_move) = move_picker.next(board) {
 
-            let new_hash = self.zobrist_hasher.update_hash(
-                board_hash,
-                &possible_move,
-                old_en_passant_chance,
-                old_castling_rights,
-                board.castling_rights
-            );
+            board.make_move(&possible_move);
 
-            let move_score = if let Some(&mut cached_score) = self.transposition_table.get(new_hash) {
+            let move_score = if board.is_repetition(2) || board.is_draw_by_fifty_moves() {
+                0
+            }
+            else if let Some(&mut cached_score) = self.transposition_table.get(board.hash()) {
                 cached_score
             }
             else {
-
-                board.make_move(&possible_move);
-                
-                let move_score = -self.find_board_score(board, depth - 1, -beta, -alpha, board_hash).0;
-                self.transposition_table.set(board_hash, move_score);
-                
-                board.undo_move();
+                let move_score = -self.find_board_score(board, depth - 1, -beta, -alpha).0;
+                self.transposition_table.set(board.hash(), move_score);
                 move_score
-                
             };
 
+            board.undo_move();
+
             if move_score > score {
                 best_move = Some(possible_move);
                 score = move_score;
@@ -108,8 +74,16 @@ impl IterativeDeepening {
             }
         }
 
+        // having no moves is a loss unless it's stalemate
+        if move_picker.moves_picked() == 0 {
+            return match board.get_outcome(&[]) {
+                Some(game::Outcome::Win(_)) => (LOSE_SCORE, None),
+                _ => (0, None)
+            };
+        }
+
         if let Some(pv_move) = &best_move {
-            self.pv_table.set(board_hash, *pv_move);
+            self.pv_table.set(board.hash(), pv_move.pack());
         }
 
         (score - score.signum(), best_move)
@@ -128,9 +102,7 @@ impl Player for IterativeDeepening {
         self.nodes_searched = 0;
         self.pv_table.clear();
         
-        let board_hash = self.zobrist_hasher.get_board_hash(board);
-
-        let mut eval = 0;
+        let mut _eval = 0;
         let mut best_move = None;
 
         let mut time_taken = 0;
@@ -142,7 +114,7 @@ impl Player for IterativeDeepening {
 
             self.transposition_table.clear();
 
-            (eval, best_move) = self.find_board_score(board, search_depth, MIN_SCORE, MAX_SCORE, board_hash);
+            (_eval, best_move) = self.find_board_score(board, search_depth, MIN_SCORE, MAX_SCORE);
 
             // println!("depth {}: {}ms", search_depth, (time::Instant::now() - start_time).as_millis());
             // println!("best move: {}, eval: {}", best_move.unwrap().to_long_an(), eval);
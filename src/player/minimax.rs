@@ -5,7 +5,6 @@ use crate::hash;
 pub struct MiniMaxPlayer {
     depth: usize,
     score_board: BoardScore,
-    zobrist_hasher: hash::Zobrist,
     transposition_table: hash::HashTable<i32, 20, 4>,
     nodes_searched: usize
 }
@@ -16,7 +15,6 @@ impl MiniMaxPlayer {
         Self{
             depth,
             score_board,
-            zobrist_hasher: hash::Zobrist::new(),
             transposition_table: hash::HashTable::new(),
             nodes_searched: 0
         }
@@ -27,7 +25,7 @@ impl MiniMaxPlayer {
         board.make_move(move_to_check);
         self.nodes_searched += 1;
 
-        let hash = self.zobrist_hasher.get_board_hash(board);
+        let hash = board.hash();
 
         return match self.transposition_table.get(hash) {
             Some(&mut score) => {
@@ -45,7 +43,10 @@ impl MiniMaxPlayer {
                     )
                     .min() {
                         Some(val) => val,
-                        None => MAX_SCORE
+                        None => match board.get_outcome(&[]) {
+                            Some(game::Outcome::Win(_)) => MAX_SCORE,
+                            _ => 0
+                        }
                     }
                 };
         
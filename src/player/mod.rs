@@ -4,6 +4,7 @@ mod randommove;
 mod minimax;
 mod alphabeta;
 mod iterativedeepening;
+mod movepicker;
 mod scoring;
 
 pub use humanplayer::*;
@@ -12,6 +13,7 @@ pub use randommove::*;
 pub use minimax::*;
 pub use alphabeta::*;
 pub use iterativedeepening::*;
+pub use movepicker::*;
 pub use scoring::*;
 
 use crate::game;
@@ -0,0 +1,196 @@
+use crate::game;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stage {
+    BestMove,
+    Captures,
+    BadCaptures,
+    Quiets,
+    Evasions,
+    Done
+}
+
+// hands out the moves of a position a stage at a time, so a cutoff
+// early on never pays for generating the quiet moves, captures that lose material by see
+// come after the rest of the captures
+pub struct MovePicker {
+    stage: Stage,
+    best_move: Option<game::PackedMove>,
+    moves: game::MoveList,
+    generated: bool,
+    idx: usize,
+    // captures that lose material wait at the front of the list until the good ones are done
+    bad_captures: usize,
+    moves_picked: usize
+}
+
+// most valuable victim first, then least valuable attacker
+fn capture_order(capture: &game::Move) -> i32 {
+
+    let victim_value = match capture.captured_piece() {
+        game::Empty => 0,
+        captured_piece => (captured_piece as i32 & 0b111) + 1
+    };
+
+    let promotion_value = capture.promotion_piece().map_or(0, |promote_piece| promote_piece as i32 & 0b111);
+
+    (victim_value + promotion_value) * 8 - (capture.moved_piece as i32 & 0b111)
+
+}
+
+fn sort_noisy_first(moves: &mut [game::Move]) {
+    moves.sort_by_key(|possible_move| if game::is_noisy(possible_move) {-capture_order(possible_move)} else {i32::MAX});
+}
+
+impl MovePicker {
+
+    // the best move, from a pv or hash table, is tried first if it's legal here
+    pub fn new(board: &game::Board, best_move: Option<game::PackedMove>) -> Self {
+
+        let stage = if board.is_in_check() {
+            Stage::Evasions
+        }
+        else if best_move.is_some() {
+            Stage::BestMove
+        }
+        else {
+            Stage::Captures
+        };
+
+        Self {
+            stage,
+            best_move,
+            moves: game::MoveList::new(),
+            generated: false,
+            idx: 0,
+            bad_captures: 0,
+            moves_picked: 0
+        }
+    }
+
+    // the board has to be in the same position as when the picker was made
+    pub fn next(&mut self, board: &game::Board) -> Option<game::Move> {
+
+        let picked_move = self.pick(board);
+
+        if picked_move.is_some() {
+            self.moves_picked += 1;
+        }
+
+        picked_move
+
+    }
+
+    pub fn moves_picked(&self) -> usize {
+        self.moves_picked
+    }
+
+    fn pick(&mut self, board: &game::Board) -> Option<game::Move> {
+        loop {
+            match self.stage {
+                Stage::BestMove => {
+
+                    self.stage = Stage::Captures;
+                    let best_move = self.best_move.unwrap().unpack(board);
+
+                    // checked on its own, so nothing has to be generated to find it
+                    if board.is_legal(&best_move) {
+                        return Some(best_move);
+                    }
+
+                    self.best_move = None;
+
+                }
+                Stage::Captures => {
+
+                    self.fill_captures(board);
+
+                    while let Some(&capture) = self.moves.get(self.idx) {
+
+                        self.idx += 1;
+
+                        if self.is_best_move(&capture) {
+                            continue;
+                        }
+
+                        if game::see_ge(board, &capture, 0) {
+                            return Some(capture);
+                        }
+
+                        self.moves[self.bad_captures] = capture;
+                        self.bad_captures += 1;
+
+                    }
+
+                    self.stage = Stage::BadCaptures;
+                    self.idx = 0;
+
+                }
+                Stage::BadCaptures => {
+
+                    if self.idx < self.bad_captures {
+                        self.idx += 1;
+                        return Some(self.moves[self.idx - 1]);
+                    }
+
+                    self.stage = Stage::Quiets;
+                    self.moves = game::get_quiet_moves(board);
+                    self.idx = 0;
+
+                }
+                Stage::Quiets => {
+
+                    while let Some(&quiet_move) = self.moves.get(self.idx) {
+
+                        self.idx += 1;
+
+                        if !self.is_best_move(&quiet_move) {
+                            return Some(quiet_move);
+                        }
+                    }
+
+                    self.stage = Stage::Done;
+
+                }
+                Stage::Evasions => {
+
+                    if !self.generated {
+
+                        self.moves = game::get_evasion_moves(board);
+                        self.generated = true;
+                        sort_noisy_first(&mut self.moves);
+
+                        if let Some(best_move) = self.best_move {
+                            if let Some(idx) = self.moves.iter().position(|possible_move| possible_move.pack() == best_move) {
+                                let best_move = self.moves.remove(idx);
+                                self.moves.insert(0, best_move);
+                            }
+                        }
+                    }
+
+                    if let Some(&evasion) = self.moves.get(self.idx) {
+                        self.idx += 1;
+                        return Some(evasion);
+                    }
+
+                    self.stage = Stage::Done;
+
+                }
+                Stage::Done => return None
+            }
+        }
+    }
+
+    // the best move has already been handed out if it was legal
+    fn is_best_move(&self, possible_move: &game::Move) -> bool {
+        self.best_move == Some(possible_move.pack())
+    }
+
+    fn fill_captures(&mut self, board: &game::Board) {
+        if !self.generated {
+            self.moves = game::get_capture_moves(board);
+            self.generated = true;
+            sort_noisy_first(&mut self.moves);
+        }
+    }
+}
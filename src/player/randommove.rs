@@ -9,7 +9,7 @@ pub struct RandomPlayer {
 
 impl player::Player for RandomPlayer {
 
-    fn get_raw_eval(&mut self, board: &Board) -> i32 {
+    fn get_raw_eval(&mut self, _board: &Board) -> i32 {
         0
     }
 
@@ -1,7 +1,7 @@
 use crate::game;
 use crate::game::Square;
 
-pub type BoardScore = &'static(dyn Fn(&game::Board) -> i32);
+pub type BoardScore = &'static dyn Fn(&game::Board) -> i32;
 
 pub const MIN_SCORE: i32 = i32::MIN + 1;
 pub const MAX_SCORE: i32 = i32::MAX;
@@ -128,43 +128,43 @@ pub fn advanced_eval(board: &game::Board) -> i32 {
 
     let mut score = 0;
 
-    for &piece in &board.piece_positions[game::WhitePawn as usize] {
+    for piece in board.get_piece_squares(game::WhitePawn) {
         score += PAWN_SQUARE_VALUES[piece as usize];
     }
 
-    for &piece in &board.piece_positions[game::BlackPawn as usize] {
+    for piece in board.get_piece_squares(game::BlackPawn) {
         score -= PAWN_SQUARE_VALUES[flip(piece) as usize];
     }
 
-    for &piece in &board.piece_positions[game::WhiteKnight as usize] {
+    for piece in board.get_piece_squares(game::WhiteKnight) {
         score += KNIGHT_SQUARE_VALUES[piece as usize];
     }
 
-    for &piece in &board.piece_positions[game::BlackKnight as usize] {
+    for piece in board.get_piece_squares(game::BlackKnight) {
         score -= KNIGHT_SQUARE_VALUES[flip(piece) as usize];
     }
 
-    for &piece in &board.piece_positions[game::WhiteBishop as usize] {
+    for piece in board.get_piece_squares(game::WhiteBishop) {
         score += BISHOP_SQUARE_VALUES[piece as usize];
     }
 
-    for &piece in &board.piece_positions[game::BlackBishop as usize] {
+    for piece in board.get_piece_squares(game::BlackBishop) {
         score -= BISHOP_SQUARE_VALUES[flip(piece) as usize];
     }
 
-    for &piece in &board.piece_positions[game::WhiteRook as usize] {
+    for piece in board.get_piece_squares(game::WhiteRook) {
         score += ROOK_SQUARE_VALUES[piece as usize];
     }
 
-    for &piece in &board.piece_positions[game::BlackRook as usize] {
+    for piece in board.get_piece_squares(game::BlackRook) {
         score -= ROOK_SQUARE_VALUES[flip(piece) as usize];
     }
 
-    for &piece in &board.piece_positions[game::WhiteQueen as usize] {
+    for piece in board.get_piece_squares(game::WhiteQueen) {
         score += QUEEN_SQUARE_VALUES[piece as usize];
     }
 
-    for &piece in &board.piece_positions[game::BlackQueen as usize] {
+    for piece in board.get_piece_squares(game::BlackQueen) {
         score -= QUEEN_SQUARE_VALUES[flip(piece) as usize];
     }
 
@@ -174,13 +174,15 @@ pub fn advanced_eval(board: &game::Board) -> i32 {
     let in_late_game = white_pieces[4] + black_pieces[4] == 0 || 
     white_pieces[2] + white_pieces[3] + black_pieces[2] + black_pieces[3] <= 2;
 
-    if in_late_game {
-        score += KING_LATE_SQUARE_VALUES[board.white_king as usize];
-        score -= KING_LATE_SQUARE_VALUES[flip(board.black_king) as usize];    
+    let king_square_values = if in_late_game {&KING_LATE_SQUARE_VALUES} else {&KING_EARLY_SQUARE_VALUES};
+
+    // a king can be missing in horde or after an explosion
+    if board.white_king != game::NO_SQUARE {
+        score += king_square_values[board.white_king as usize];
     }
-    else {
-        score += KING_EARLY_SQUARE_VALUES[board.white_king as usize];
-        score -= KING_EARLY_SQUARE_VALUES[flip(board.black_king) as usize];
+
+    if board.black_king != game::NO_SQUARE {
+        score -= king_square_values[flip(board.black_king) as usize];
     }
 
     score *= -board.side_to_move.to_dir() as i32;
@@ -2,6 +2,32 @@ extern crate chess;
 
 const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+// between them every kind of move, for the tests that check something of each legal one
+const MOVE_FIXTURES: [(chess::game::Variant, &str); 10] = [
+    (chess::game::Variant::Standard, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+    (chess::game::Variant::Standard, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+    (chess::game::Variant::Standard, "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1"),
+    (chess::game::Variant::Standard, "1rk3r1/8/8/8/8/8/8/1RK3R1 w GBgb - 0 1"),
+    (chess::game::Variant::Standard, "8/8/8/7k/8/Q7/8/Q1Q4K w - - 0 1"),
+    (chess::game::Variant::Standard, "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4"),
+    (chess::game::Variant::Atomic, "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+    (chess::game::Variant::Atomic, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+    (chess::game::Variant::ThreeCheck, "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 4 3"),
+    (chess::game::Variant::Crazyhouse, "r1bqkbnr/ppp2ppp/2n5/3pp3/4P3/5N2/PPPP1PPP/RNBQKB1R[Pnb] w KQkq - 3 4")
+];
+
+// the board is handed back in the position the move is from
+fn for_each_fixture_move(mut check: impl FnMut(&mut chess::game::Board, &chess::game::Move)) {
+    for (variant, fen) in MOVE_FIXTURES {
+
+        let mut board = chess::game::Board::from_fen_variant(fen, variant);
+
+        for possible_move in chess::game::get_possible_moves(&board) {
+            check(&mut board, &possible_move);
+        }
+    }
+}
+
 #[test]
 fn shannon_number_1ply() {
     assert_eq!(chess::game::get_num_moves(&mut chess::game::Board::from_fen(String::from(START_POS)), 2), 400);
@@ -22,11 +48,9 @@ fn position_2_2ply() {
     assert_eq!(chess::game::get_num_moves(&mut chess::game::Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ")), 4), 4085603);
 }
 
-// disabled until i fix en-passant
 #[test]
-#[ignore = "have not implemented en-passant pins"]
 fn position_3_2ply() {
-    assert_eq!(chess::game::get_num_moves(&mut chess::game::Board::from_fen(String::from("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ")), 4), 4085603);
+    assert_eq!(chess::game::get_num_moves(&mut chess::game::Board::from_fen(String::from("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ")), 4), 43238);
 }
 
 #[test]
@@ -34,12 +58,30 @@ fn position_6_2ply() {
     assert_eq!(chess::game::get_num_moves(&mut chess::game::Board::from_fen(String::from("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ")), 4), 3894594);
 }
 
+#[test]
+fn perft_suite() {
+
+    for line in include_str!("data/perft.txt").lines() {
+
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split(';').collect();
+        let depth = fields[1].parse().unwrap();
+        let expected: u64 = fields[2].parse().unwrap();
+
+        let mut board = chess::game::Board::from_fen(String::from(fields[0]));
+        assert_eq!(chess::game::get_num_moves(&mut board, depth), expected, "{}", line);
+    }
+}
+
 #[test]
 fn capture_queen_alpha_beta() {
     let mut board = chess::game::Board::from_fen(String::from("rnb1kbnr/pppppppp/8/8/3Qq3/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1"));
     let mut alphabeta: Box<dyn chess::player::Player> = Box::new(chess::player::AlphaBetaPlayer::new(6, &chess::player::basic_eval));
     let possible_moves = chess::game::get_possible_moves(&board);
-    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().to_long_an(), "d4e4");
+    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "d4e4");
 }
 
 #[test]
@@ -47,7 +89,18 @@ fn capture_queen_iterative_deepening() {
     let mut board = chess::game::Board::from_fen(String::from("rnb1kbnr/pppppppp/8/8/3Qq3/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1"));
     let mut alphabeta: Box<dyn chess::player::Player> = Box::new(chess::player::IterativeDeepening::new(1000, &chess::player::basic_eval));
     let possible_moves = chess::game::get_possible_moves(&board);
-    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().to_long_an(), "d4e4");
+    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "d4e4");
+}
+
+#[test]
+fn variant_win_at_leaf() {
+    // taking the queen looks better to the eval, but stepping onto the hill ends the game
+    let mut board = chess::game::Board::from_fen_variant("4k3/8/8/8/8/4K3/5q2/8 w - - 0 1", chess::game::Variant::KingOfTheHill);
+    let possible_moves = chess::game::get_possible_moves(&board);
+    let mut alphabeta: Box<dyn chess::player::Player> = Box::new(chess::player::AlphaBetaPlayer::new(1, &chess::player::advanced_eval));
+    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "e3e4");
+    let mut iterative_deepening: Box<dyn chess::player::Player> = Box::new(chess::player::IterativeDeepening::new(100, &chess::player::advanced_eval));
+    assert_eq!(iterative_deepening.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "e3e4");
 }
 
 #[test]
@@ -73,32 +126,1210 @@ fn hashing_update() {
     let zobrist = chess::hash::Zobrist::new();
     let mut board = chess::game::Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - "));
 
-    let mut hash = zobrist.get_board_hash(&board);
-    let original_hash = hash;
+    let original_hash = board.hash();
+    assert_eq!(original_hash, zobrist.get_board_hash(&board));
 
-    let old_en_passant_chance1 = board.en_passant_chance;
-    let mut old_castling_rights1 = board.castling_rights;
-    
-    let move1 = chess::game::Move::new_castle(&board, chess::game::E1, chess::game::G1);
+    let move1 = chess::game::Move::new_castle(&board, chess::game::E1, chess::game::H1);
     board.make_move(&move1);
-
-    hash = zobrist.update_hash(hash, &move1, old_en_passant_chance1, old_castling_rights1, board.castling_rights);
-    old_castling_rights1 = board.castling_rights;
-
-    let old_en_passant_chance2 = board.en_passant_chance;
-    let mut old_castling_rights2 = board.castling_rights;
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
 
     let move2 = chess::game::Move::new_pawn_double(&board, chess::game::C7, chess::game::C5);
     board.make_move(&move2);
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
 
-    hash = zobrist.update_hash(hash, &move2, old_en_passant_chance2, old_castling_rights2, board.castling_rights);
-    old_castling_rights2 = board.castling_rights;
+    board.undo_move();
+    board.undo_move();
+    assert_eq!(board.hash(), original_hash);
+
+}
+
+#[test]
+fn fifty_move_rule() {
+    let fen = "8/8/4k3/8/8/4K3/4P3/8 w - - 99 80";
+    let mut board = chess::game::Board::from_fen(String::from(fen));
+    assert_eq!(board.get_fen(), fen);
+    assert!(!board.is_draw_by_fifty_moves());
+
+    let king_move = chess::game::Move::new(&board, chess::game::E3, chess::game::D3);
+    board.make_move(&king_move);
+    assert_eq!(board.halfmove_clock, 100);
+    assert!(board.is_draw_by_fifty_moves());
 
     board.undo_move();
-    hash = zobrist.update_hash(hash, &move2, old_en_passant_chance2, old_castling_rights2, board.castling_rights);
+    assert_eq!(board.get_fen(), fen);
+
+    let pawn_move = chess::game::Move::new_pawn_double(&board, chess::game::E2, chess::game::E4);
+    board.make_move(&pawn_move);
+    assert_eq!(board.halfmove_clock, 0);
+
+    // mate on the hundredth half-move is still mate
+    let fen = "7k/8/6K1/8/8/8/8/R7 w - - 99 80";
+    let mut board = chess::game::Board::from_fen(String::from(fen));
+    let mate = chess::game::Move::new(&board, chess::game::A1, chess::game::A8);
+    board.make_move(&mate);
+    assert_eq!(board.halfmove_clock, 100);
+    assert!(!board.is_draw_by_fifty_moves());
+    assert!(!board.is_draw());
+
+    let mut board = chess::game::Board::from_fen(String::from(fen));
+    let possible_moves = chess::game::get_possible_moves(&board);
+    let mut alphabeta: Box<dyn chess::player::Player> = Box::new(chess::player::AlphaBetaPlayer::new(2, &chess::player::basic_eval));
+    assert_eq!(alphabeta.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "a1a8");
+    let mut iterative_deepening: Box<dyn chess::player::Player> = Box::new(chess::player::IterativeDeepening::new(100, &chess::player::basic_eval));
+    assert_eq!(iterative_deepening.get_move(&mut board, &possible_moves).unwrap().as_long_an(), "a1a8");
+}
+
+#[test]
+fn threefold_repetition() {
+    let mut board = chess::game::Board::default();
+
+    let shuffle = [
+        (chess::game::G1, chess::game::F3),
+        (chess::game::G8, chess::game::F6),
+        (chess::game::F3, chess::game::G1),
+        (chess::game::F6, chess::game::G8)
+    ];
+
+    for repeat in 0..2 {
+        for (start_square, end_square) in shuffle {
+            assert!(!board.is_draw());
+            let knight_move = chess::game::Move::new(&board, start_square, end_square);
+            board.make_move(&knight_move);
+        }
+        assert!(board.is_repetition(repeat + 2));
+    }
+
+    assert!(board.is_draw());
 
     board.undo_move();
-    hash = zobrist.update_hash(hash, &move1, old_en_passant_chance1, old_castling_rights1, board.castling_rights);
-    assert_eq!(hash, original_hash);
+    assert!(!board.is_draw());
+    assert!(board.is_repetition(2));
+}
+
+#[test]
+fn fen_errors() {
+    use chess::game::{Board, FenError, Colour};
+
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1").err(), Some(FenError::BadPiece('X')));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::BadRankLength(6)));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").err(), Some(FenError::BadRankCount));
+    assert_eq!(Board::parse_fen("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").err(), Some(FenError::MissingKing(Colour::Black)));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1").err(), Some(FenError::BadSideToMove));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkz - 0 1").err(), Some(FenError::BadCastling('z')));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1").err(), Some(FenError::BadEnPassant));
+    assert_eq!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").err(), Some(FenError::MissingField));
+
+    // no pawn could have just skipped over e6
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 w - e6 0 1").err(), Some(FenError::BadEnPassant));
+    assert_eq!(Board::parse_fen("4k3/4p3/8/4p3/8/8/8/4K3 w - e6 0 1").err(), Some(FenError::BadEnPassant));
+    assert!(Board::parse_fen("4k3/8/8/4p3/8/8/8/4K3 w - e6 0 1").is_ok());
+
+    // turns_taken would overflow
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 4000000000").err(), Some(FenError::BadFullmoveNumber));
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 2147483648").err(), Some(FenError::BadFullmoveNumber));
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0").err(), Some(FenError::BadFullmoveNumber));
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1000000000").map(|board| board.get_fen()), Ok(String::from("4k3/8/8/8/8/8/8/4K3 b - - 0 1000000000")));
+
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 3 12";
+    let board: Board = fen.parse().unwrap();
+    assert_eq!(board.to_string(), fen);
+}
+
+#[test]
+fn chess960_perft() {
+
+    let positions = [
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 326672),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", 273318),
+        ("1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9", 287739),
+        ("4k3/8/8/8/8/8/8/qRK5 w B - 0 1", 19079),
+        ("1r1k2r1/8/8/8/8/8/8/R2K3R w HAgb - 0 1", 354643)
+    ];
+
+    for (fen, expected) in positions {
+        let mut board = chess::game::Board::from_fen(String::from(fen));
+        assert!(board.chess960);
+        assert_eq!(chess::game::get_num_moves(&mut board, 4), expected, "{}", fen);
+    }
+}
+
+#[test]
+fn chess960_fen() {
+
+    let board = chess::game::Board::from_fen(String::from("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"));
+    assert_eq!(board.get_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+
+    // the inner rook needs its file spelled out
+    let fen = "4k3/8/8/8/8/8/8/1K2R2R w E - 0 1";
+    assert_eq!(chess::game::Board::from_fen(String::from(fen)).get_fen(), fen);
+
+    assert!(!chess::game::Board::from_fen(String::from(START_POS)).chess960);
+}
+
+#[test]
+fn castling_notation() {
+
+    let mut board = chess::game::Board::from_fen(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+
+    let castle = chess::game::Move::from_long_an("e1g1", &board);
+    assert_eq!(castle.move_type, chess::game::MoveType::Castle);
+    assert_eq!(castle.end_square, chess::game::H1);
+    assert_eq!(castle.as_uci(false), "e1g1");
+    assert_eq!(castle.as_uci(true), "e1h1");
+
+    let castle = chess::game::Move::from_long_an("e1a1", &board);
+    assert_eq!(castle.move_type, chess::game::MoveType::Castle);
+    assert_eq!(castle.as_uci(false), "e1c1");
+
+    board.make_move(&castle);
+    assert_eq!(board.get_fen(), "r3k2r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+    board.undo_move();
+    assert_eq!(board.get_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+}
+
+#[test]
+fn position_validation() {
+    use chess::game::{Board, FenError, PositionProblem, White, Black};
+
+    assert!(Board::default().validate().is_empty());
+    assert!(Board::from_fen(String::from("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")).validate().is_empty());
+    assert!(Board::from_fen(String::from("rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPP2PPP/RNBQKBNR b KQkq e3 0 3")).validate().is_empty());
+
+    let problems = |fen: &str| Board::from_fen(String::from(fen)).validate();
+
+    assert_eq!(problems("4k3/8/8/8/8/8/8/K3K3 w - - 0 1"), vec![PositionProblem::KingCount(White, 2)]);
+    assert_eq!(problems("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"), vec![PositionProblem::PawnOnBackRank(chess::game::A8)]);
+    assert_eq!(problems("4k3/8/8/8/8/8/8/4K2R w - - 0 1"), vec![]);
+    assert_eq!(problems("4k3/8/8/8/8/8/8/4R2K w - - 0 1"), vec![PositionProblem::OpponentInCheck]);
+    assert_eq!(problems("4k3/8/8/8/8/8/8/4K3 w K - 0 1"), vec![PositionProblem::CastlingWithoutRook(chess::game::WHITE_KINGSIDE)]);
+    assert_eq!(problems("4k3/8/8/8/8/8/4K3/7R w K - 0 1"), vec![PositionProblem::CastlingWithoutKing(chess::game::WHITE_KINGSIDE)]);
+    assert_eq!(Board::parse_fen("4k3/8/8/8/8/8/8/4K3 b - e3 0 1").err(), Some(FenError::BadEnPassant));
+    assert_eq!(problems("4k3/8/8/8/8/PPPPPPPP/P7/4K3 w - - 0 1"), vec![PositionProblem::TooManyPawns(White)]);
+    assert_eq!(problems("qqqqkqqq/qqq5/8/8/8/8/8/4K3 w - - 0 1"), vec![PositionProblem::TooManyPieces(Black)]);
+
+    assert_eq!(Board::parse_fen_strict("4k3/8/8/8/8/8/8/4R2K w - - 0 1").err(), Some(FenError::IllegalPosition(PositionProblem::OpponentInCheck)));
+    assert!(Board::parse_fen_strict(START_POS).is_ok());
+}
+
+#[test]
+fn board_builder() {
+    use chess::game::*;
+
+    let board = BoardBuilder::new()
+        .piece(E1, WhiteKing)
+        .piece(H1, WhiteRook)
+        .piece(E2, WhitePawn)
+        .piece(E8, BlackKing)
+        .castling(WHITE_KINGSIDE)
+        .side_to_move(Black)
+        .halfmove_clock(3)
+        .fullmove_number(20)
+        .build()
+        .unwrap();
+
+    assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/4P3/4K2R b K - 3 20");
+    assert_eq!(board.get_piece_bitboard(WhiteRook), square_bitboard(H1));
+    assert_eq!(board.get_king_square(Black), E8);
+    assert_eq!(board.hash(), chess::hash::Zobrist::new().get_board_hash(&board));
+    assert_eq!(get_num_moves(&mut board.clone(), 3), get_num_moves(&mut Board::from_fen(board.get_fen()), 3));
+
+    let mut editor = BoardBuilder::from_board(&Board::default());
+    editor.remove_piece(E2).piece(E4, WhitePawn).side_to_move(Black).en_passant(Some(E3));
+    assert_eq!(editor.build().unwrap().get_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+    editor.clear().no_castling().piece(E1, WhiteKing);
+    assert_eq!(editor.build().err(), Some(vec![PositionProblem::KingCount(Black, 0), PositionProblem::BadEnPassant(E3)]));
+
+    // everything a variant keeps beyond the pieces comes through
+    let positions = [
+        (Variant::Standard, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+        (Variant::KingOfTheHill, "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"),
+        (Variant::ThreeCheck, "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 2+1 0 3"),
+        (Variant::Atomic, "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+        (Variant::Horde, Variant::Horde.get_start_fen()),
+        (Variant::Crazyhouse, "r1bqkbnr/ppp2ppp/2n5/3pp3/4P3/5N2/PPPP1PPP/RNBQ~KB1R[Pnb] w Qkq - 0 4")
+    ];
+
+    for (variant, fen) in positions {
+
+        let board = Board::from_fen_variant(fen, variant);
+        let rebuilt = BoardBuilder::from_board(&board).build().unwrap();
+
+        assert_eq!(rebuilt.variant, variant);
+        assert_eq!(rebuilt.get_fen(), board.get_fen());
+        assert_eq!(rebuilt.hash(), board.hash(), "{}", fen);
+    }
+
+    let board = BoardBuilder::new()
+        .variant(Variant::Crazyhouse)
+        .piece(E1, WhiteKing)
+        .piece(E8, BlackKing)
+        .piece(D1, WhiteQueen)
+        .promoted(D1, true)
+        .promoted(D2, true)
+        .pocket(BlackKnight, 2)
+        .build()
+        .unwrap();
+
+    assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/8/3Q~K3[nn] w - - 0 1");
+    assert!(board.is_promoted(D1) && !board.is_promoted(D2));
+
+    let board = BoardBuilder::from_board(&Board::new_variant(Variant::ThreeCheck)).remaining_checks(Black, 1).build().unwrap();
+    assert_eq!(board.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+1 0 1");
+}
+
+#[test]
+fn null_move() {
+
+    let zobrist = chess::hash::Zobrist::new();
+    let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3";
+    let mut board = chess::game::Board::from_fen(String::from(fen));
+    let original_hash = board.hash();
+
+    board.make_null_move();
+    assert_eq!(board.side_to_move, chess::game::White);
+    assert_eq!(board.en_passant_chance, None);
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+
+    // the en passant square only comes back because the null move remembered it
+    assert!(board.undo_null_move().is_some());
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(board.hash(), original_hash);
+
+    let knight_move = chess::game::Move::new(&board, chess::game::G8, chess::game::F6);
+    board.make_move(&knight_move);
+    assert!(board.undo_null_move().is_none());
+    board.undo_move();
+    assert_eq!(board.get_fen(), fen);
+
+    // two passes get back to the same position, but that isn't a repetition
+    let mut board = chess::game::Board::from_fen(String::from(START_POS));
+    board.make_null_move();
+    board.make_null_move();
+    assert!(!board.is_repetition(2));
+}
+
+#[test]
+fn variant_perft() {
+    use chess::game::{Board, Variant};
+
+    // checked against another move generator
+    let positions = [
+        (Variant::KingOfTheHill, "rn2kb2/Q3q2r/2p3p1/3p1p1p/1P3PnP/P2p4/b2B4/3B1KNR w q - 1 29", 3, 36514),
+        (Variant::ThreeCheck, "rn2kb2/Q3q2r/2p3p1/3p1p1p/1P3PnP/P2p4/b2B4/3B1KNR w q - 2+3 1 29", 3, 36514),
+        (Variant::ThreeCheck, "rn2kb2/Q3q2r/2p3p1/3p1p1p/1P3PnP/P2p4/b2B4/3B1KNR w q - 1+3 1 29", 3, 36266),
+        (Variant::Atomic, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 4, 197326),
+        (Variant::Atomic, "1n1k4/2p5/2r3p1/p2pp2p/5r1P/NP1P1B2/K6R/6N1 w - - 2 29", 3, 20942),
+        (Variant::Atomic, "1n1r4/rbpk3p/4p3/p3P1pP/8/5Pb1/1PPPK1B1/1RBQ2R1 w - - 1 29", 3, 12301),
+        (Variant::Horde, "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1", 4, 23310),
+        (Variant::Horde, "1q1k2N1/1Pp1r3/1P6/1rPbppPp/1PPPPP1P/P1PP1P1P/P1P1PPPP/1PPPPPPP b - - 0 26", 3, 14121),
+        (Variant::Crazyhouse, "2q1k3/1P6/8/8/8/8/8/4K3[Rp] w - - 0 1", 3, 126700),
+        (Variant::Crazyhouse, "r1b1k1r1/1q1p1p1n/pp4Br/p3p1Pp/PQ5n/n1p1P3/P1B2KP1/q~1B1N1R1[Pp] b q - 0 39", 3, 224873),
+        (Variant::Crazyhouse, "q5kr/1r2pb2/n2P4/p1p1n1b1/pPpPBp1P/P1P2PnR/PPN1P3/R3K1B1[q] b Q - 1 36", 3, 166336)
+    ];
+
+    for (variant, fen, depth, expected) in positions {
+        let mut board = Board::from_fen_variant(fen, variant);
+        assert_eq!(chess::game::get_num_moves(&mut board, depth), expected, "{} {}", variant, fen);
+    }
+}
+
+#[test]
+fn variant_rules() {
+    use chess::game::*;
+
+    let zobrist = chess::hash::Zobrist::new();
+
+    // reaching the centre wins king of the hill
+    let board = Board::from_fen_variant("4k3/8/8/8/4K3/8/8/8 b - - 0 1", Variant::KingOfTheHill);
+    assert!(get_possible_moves(&board).is_empty());
+    assert_eq!(board.get_outcome(&[]), Some(Outcome::Win(White)));
+
+    // three-check counts down with each check and takes lichess' counters too
+    let fen = "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1+3 0 3";
+    let mut board = Board::from_fen_variant(fen, Variant::ThreeCheck);
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(Board::from_fen_variant("rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3 +2+0", Variant::ThreeCheck).get_fen(), fen);
+
+    board.make_move(&Move::from_long_an("f1b5", &board));
+    assert_eq!(board.get_remaining_checks(White), 0);
+    assert_eq!(board.get_variant_win(), Some(White));
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+    board.undo_move();
+    assert_eq!(board.get_fen(), fen);
+
+    // an atomic capture next to the king blows it up
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 4";
+    let mut board = Board::from_fen_variant(fen, Variant::Atomic);
+    let original_hash = board.hash();
+
+    board.make_move(&Move::from_long_an("h5f7", &board));
+    assert_eq!(board.get_fen(), "r1bq3r/pppp2pp/2n5/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQ - 0 4");
+    assert_eq!(board.get_outcome(&get_possible_moves(&board)), Some(Outcome::Win(White)));
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+
+    // the blown up king isn't left behind on its old square
+    assert_eq!(board.get_king_square(Black), NO_SQUARE);
+    assert_eq!(board.get_king_square(White), E1);
+    assert!(!board.is_in_check());
+    assert_eq!(get_checkers(&board, Black), 0);
+    chess::player::advanced_eval(&board);
+
+    board.undo_move();
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(board.hash(), original_hash);
+    assert_eq!(board.get_king_square(Black), E8);
+
+    // the horde loses once it has nothing left
+    let board = Board::from_fen_variant("4k3/8/8/8/8/8/8/8 w - - 0 1", Variant::Horde);
+    assert!(board.validate().is_empty());
+    assert_eq!(board.get_outcome(&get_possible_moves(&board)), Some(Outcome::Win(Black)));
+
+    assert_eq!(Variant::from_name("3check"), Some(Variant::ThreeCheck));
+    assert!(Board::new_variant(Variant::Horde).validate().is_empty());
+}
+
+#[test]
+fn crazyhouse() {
+    use chess::game::*;
+
+    let zobrist = chess::hash::Zobrist::new();
+
+    // pockets in brackets or as a ninth rank, ~ for promoted pieces
+    let fen = "r3k2r/1P6/8/8/8/8/8/R3K2R[NPqp] w KQkq - 0 1";
+    let mut board = Board::from_fen_variant(fen, Variant::Crazyhouse);
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(Board::from_fen_variant("r3k2r/1P6/8/8/8/8/8/R3K2R/NPpq w KQkq - 0 1", Variant::Crazyhouse).get_fen(), fen);
+    assert_eq!(board.get_pocket_count(WhiteKnight), 1);
+    assert_eq!(board.get_pocket_count(BlackQueen), 1);
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+    assert_eq!(Board::parse_fen_variant("8/8/8/8/8/8/8/K6k[Kk] w - - 0 1", Variant::Crazyhouse).err(), Some(FenError::BadPocket('K')));
+    assert!(Board::parse_fen("r3k2r/1P6/8/8/8/8/8/R3K2R[NPpq] w KQkq - 0 1").is_err());
+
+    // drops in uci and san, pawns can't go on the back ranks
+    let possible_moves = get_possible_moves(&board);
+    let drop = Move::from_long_an("N@e4", &board);
+    assert_eq!(drop.move_type, MoveType::Drop(WhiteKnight));
+    assert_eq!(drop.as_uci(false), "N@e4");
+    assert_eq!(drop.as_an(&possible_moves), "N@e4");
+    assert_eq!(Move::from_an("P@e4", &board).unwrap().as_long_an(), "P@e4");
+    assert_eq!(Move::from_an("@e4", &board).unwrap().as_long_an(), "P@e4");
+    assert!(Move::from_an("P@e8", &board).is_none());
+    assert!(Move::from_an("P@e1", &board).is_none());
+    assert!(Move::from_an("Q@e4", &board).is_none());
+
+    board.make_move(&drop);
+    assert_eq!(board.get_fen(), "r3k2r/1P6/8/8/4N3/8/8/R3K2R[Pqp] b KQkq - 1 1");
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+    board.undo_move();
+    assert_eq!(board.get_fen(), fen);
+
+    // a promoted piece goes back into the pocket as a pawn
+    board.make_move(&Move::from_an("bxa8=Q", &board).unwrap());
+    assert_eq!(board.get_fen(), "Q~3k2r/8/8/8/8/8/8/R3K2R[RNPqp] b KQk - 0 1");
+    board.make_move(&Move::from_an("Q@b8", &board).unwrap());
+    board.make_move(&Move::from_long_an("e1g1", &board));
+    board.make_move(&Move::from_an("Qxa8", &board).unwrap());
+    assert_eq!(board.get_fen(), "q3k2r/8/8/8/8/8/8/R4RK1[RNPpp] w k - 0 3");
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+
+    for _ in 0..4 {
+        board.undo_move();
+    }
+
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(board.hash(), zobrist.get_board_hash(&board));
+
+    // a drop can block a check, but not a double check
+    let board = Board::from_fen_variant("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1", Variant::Crazyhouse);
+    assert_eq!(get_possible_moves(&board).iter().filter(|legal_move| matches!(legal_move.move_type, MoveType::Drop(_))).count(), 3);
+    let board = Board::from_fen_variant("4k3/8/8/8/8/3n4/8/r3K3[N] w - - 0 1", Variant::Crazyhouse);
+    assert!(get_possible_moves(&board).iter().all(|legal_move| legal_move.moved_piece == WhiteKing));
+
+    assert!(!board.is_draw_by_insufficient_material());
+}
+
+#[test]
+fn packed_positions() {
+    use chess::game::*;
+
+    let fens = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Variant::Standard),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", Variant::Standard),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", Variant::Standard),
+        ("2r1kr2/8/8/8/8/8/8/1R2K1R1 w GBf - 17 301", Variant::Standard),
+        ("rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1+3 0 3", Variant::ThreeCheck),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1", Variant::Crazyhouse)
+    ];
+
+    for (fen, variant) in fens {
+        let board = Board::from_fen_variant(fen, variant);
+        let decoded = board.encode().unwrap().decode().unwrap();
+        assert_eq!(decoded.get_fen(), board.get_fen());
+        assert_eq!(decoded.hash(), board.hash());
+        assert_eq!(decoded.variant, variant);
+    }
+
+    assert_eq!(Board::new_variant(Variant::Horde).encode(), Err(PackError::TooManyPieces(52)));
+    assert_eq!(Board::from_fen_variant("4k3/8/8/8/8/8/8/4K3[Q] w - - 0 1", Variant::Crazyhouse).encode(), Err(PackError::PocketsNotEmpty));
+    assert_eq!(Board::from_fen_variant("4k3/8/8/8/8/8/8/3Q~K3[] w - - 0 1", Variant::Crazyhouse).encode(), Err(PackError::PocketsNotEmpty));
+    assert_eq!(PackedBoard([0; 32]).decode().err(), Some(PackError::MissingKing(White)));
+
+    // atomic lets a king be missing, but not one that a castling rook needs
+    let mut packed = Board::from_fen_variant("4k3/8/8/8/8/8/8/4K2R w K - 0 1", Variant::Atomic).encode().unwrap();
+    let occupied = u64::from_le_bytes(packed.0[0..8].try_into().unwrap()) & !(1 << 60);
+    packed.0[0..8].copy_from_slice(&occupied.to_le_bytes());
+    packed.0[8] = packed.0[8] & 0xF | packed.0[9] << 4;
+    packed.0[9] = 0;
+    assert_eq!(packed.decode().err(), Some(PackError::MissingKing(White)));
+
+    // records keep whichever extras they were given
+    let mut writer = PositionWriter::new(Vec::new());
+    let mut records = Vec::new();
+
+    for (idx, (fen, variant)) in fens.into_iter().enumerate() {
+        let mut record = PositionRecord::new(&Board::from_fen_variant(fen, variant)).unwrap();
+        record.score = (idx % 2 == 0).then_some(idx as i32 * -37);
+        record.outcome = [None, Some(Outcome::Draw), Some(Outcome::Win(White)), Some(Outcome::Win(Black))][idx % 4];
+        writer.write(&record).unwrap();
+        records.push(record);
+    }
+
+    let bytes = writer.into_inner();
+    assert_eq!(bytes.len(), 6 * 33 + 3 * 4);
+
+    let read_back: Vec<_> = PositionReader::new(bytes.as_slice()).collect::<Result<_, _>>().unwrap();
+    assert_eq!(read_back, records);
+
+    let mut truncated = PositionReader::new(&bytes[..40]);
+    assert!(truncated.read().unwrap().is_some());
+    assert!(truncated.read().is_err());
+}
+
+#[test]
+fn board_rendering() {
+    use chess::game::*;
+
+    let mut board = Board::default();
+    board.make_move(&Move::from_long_an("e2e4", &board));
+
+    let rendered = board.render(&RenderOptions::default());
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines[0], "8  r  n  b  q  k  b  n  r ");
+    assert_eq!(lines[4], "4  .  .  .  . [P] .  .  . ");
+    assert_eq!(lines[6], "2  P  P  P  P [.] P  P  P ");
+    assert_eq!(lines[8], "   a  b  c  d  e  f  g  h ");
+    assert_eq!(lines[9], "Black to move");
+
+    // black's side at the bottom, with the squares a knight on g8 reaches marked
+    let options = RenderOptions {
+        flipped: true,
+        highlight_last_move: false,
+        attack_mask: 1 << F6 | 1 << H6 | 1 << E7,
+        ..RenderOptions::default()
+    };
+    let rendered = board.render(&options);
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines[0], "1  R  N  B  K  Q  B  N  R ");
+    assert_eq!(lines[5], "6  x  .  x  .  .  .  .  . ");
+    assert_eq!(lines[6], "7  p  p  p <p> p  p  p  p ");
+    assert_eq!(lines[8], "   h  g  f  e  d  c  b  a ");
+
+    let options = RenderOptions {
+        unicode: true,
+        coordinates: false,
+        side_to_move: false,
+        ..RenderOptions::default()
+    };
+    assert_eq!(board.render(&options).lines().next(), Some(" ♜  ♞  ♝  ♛  ♚  ♝  ♞  ♜ "));
+    assert!(board.render(&RenderOptions::fancy()).contains("\x1b["));
+
+    // the king in check is picked out
+    let board = Board::from_fen(String::from("rnbqkbnr/ppppp2p/5p2/6pQ/3PP3/8/PPP2PPP/RNB1KBNR b KQkq - 1 3"));
+    let rendered = board.render(&RenderOptions::default());
+    assert!(rendered.starts_with("8  r  n  b  q (k) b  n  r "));
+    assert!(rendered.ends_with("Black to move, in check\n"));
+}
+
+#[test]
+fn staged_generation() {
+    use chess::game::*;
+
+    let key = |possible_move: &Move| possible_move.as_long_an();
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"
+    ] {
+        let board = Board::from_fen(String::from(fen));
 
-}
\ No newline at end of file
+        let captures = get_capture_moves(&board);
+        let quiets = get_quiet_moves(&board);
+        assert!(captures.iter().all(is_noisy));
+        assert!(!quiets.iter().any(is_noisy));
+
+        let mut staged: Vec<_> = captures.iter().chain(&quiets).map(key).collect();
+        let mut all: Vec<_> = get_possible_moves(&board).iter().map(key).collect();
+        staged.sort();
+        all.sort();
+        assert_eq!(staged, all);
+    }
+
+    // a quiet promotion is still picked up with the captures
+    let board = Board::from_fen(String::from("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"));
+    assert_eq!(get_capture_moves(&board).len(), 4);
+
+    let board = Board::from_fen(String::from("3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+    assert!(get_evasion_moves(&board).is_empty());
+    let mut checks: Vec<_> = get_quiet_checks(&board).iter().map(key).collect();
+    checks.sort();
+    assert_eq!(checks, ["a1a8", "a1d1", "e1c1", "h1h8"]);
+
+    // in check every legal move is an evasion, whether it's the king moving, a capture, a block or a drop
+    for (fen, variant) in [
+        ("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1", Variant::Standard),
+        ("4k3/8/8/8/8/5n2/8/R3K2r w Q - 0 1", Variant::Standard),
+        ("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", Variant::Standard),
+        ("4k3/8/8/8/1b6/8/3N4/r3K3 w - - 0 1", Variant::Standard),
+        ("r3k1nr/3P4/8/8/8/8/8/4K3 b kq - 0 1", Variant::Standard),
+        ("rnbqkbnr/ppp2ppp/8/1B1pp3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 3", Variant::Standard),
+        ("4k3/8/8/8/8/8/8/r3K3[Nn] w - - 0 1", Variant::Crazyhouse)
+    ] {
+        let board = Board::from_fen_variant(fen, variant);
+        assert!(board.is_in_check(), "{}", fen);
+
+        let mut evasions: Vec<_> = get_evasion_moves(&board).iter().map(key).collect();
+        let mut all: Vec<_> = get_possible_moves(&board).iter().map(key).collect();
+        evasions.sort();
+        all.sort();
+        assert_eq!(evasions, all, "{}", fen);
+    }
+}
+
+#[test]
+fn move_picker() {
+    use chess::game::*;
+
+    let board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"));
+    let quiet_move = Move::from_long_an("a2a3", &board);
+
+    let mut move_picker = chess::player::MovePicker::new(&board, Some(quiet_move.pack()));
+    let mut picked = Vec::new();
+
+    while let Some(possible_move) = move_picker.next(&board) {
+        picked.push(possible_move);
+    }
+
+    // the best move, the captures that don't lose anything, the ones that do, then the rest
+    assert_eq!(picked[0].as_long_an(), "a2a3");
+    assert_eq!(picked.len(), get_possible_moves(&board).len());
+    assert_eq!(move_picker.moves_picked(), picked.len());
+
+    let capture_count = get_capture_moves(&board).len();
+    assert!(picked[1..=capture_count].iter().all(is_noisy));
+    assert!(!picked[capture_count + 1..].iter().any(|possible_move| is_noisy(possible_move) || possible_move.as_long_an() == "a2a3"));
+
+    let good_count = picked[1..=capture_count].iter().take_while(|capture| see_ge(&board, capture, 0)).count();
+    assert!(good_count > 0 && good_count < capture_count);
+    assert!(!picked[good_count + 1..=capture_count].iter().any(|capture| see_ge(&board, capture, 0)));
+
+    // a capture as the best move isn't picked again, and a best move that can't be played here is left out
+    let start_board = Board::from_fen(String::from(START_POS));
+
+    for best_move in [Move::from_long_an("d5e6", &board).pack(), Move::from_long_an("e2e4", &start_board).pack()] {
+
+        let mut move_picker = chess::player::MovePicker::new(&board, Some(best_move));
+        let mut picked = std::collections::HashSet::new();
+
+        while let Some(possible_move) = move_picker.next(&board) {
+            assert!(picked.insert(possible_move.pack()));
+        }
+
+        assert_eq!(picked.len(), get_possible_moves(&board).len());
+    }
+
+    assert!(board.is_legal(&Move::from_long_an("a2a3", &board)));
+    assert!(!board.is_legal(&PackedMove::unpack(Move::from_long_an("e2e4", &start_board).pack(), &board)));
+
+    // pinned to the king, and a drop outside crazyhouse
+    let pinned_board = Board::from_fen(String::from("4k3/8/8/8/1b6/8/3N4/r2RK3 w - - 0 1"));
+    assert!(!pinned_board.is_legal(&Move::new(&pinned_board, D2, B3)));
+    assert!(pinned_board.is_legal(&Move::new(&pinned_board, E1, F2)));
+    assert!(!pinned_board.is_legal(&Move::new_drop(&pinned_board, WhiteKnight, C3)));
+
+    // the queen is taken by the least valuable piece first
+    let board = Board::from_fen(String::from("4k3/8/8/3q4/4P3/2N5/8/3RK3 w - - 0 1"));
+    let mut move_picker = chess::player::MovePicker::new(&board, None);
+    assert_eq!(move_picker.next(&board).unwrap().as_long_an(), "e4d5");
+    assert_eq!(move_picker.next(&board).unwrap().as_long_an(), "c3d5");
+}
+
+#[test]
+fn move_list() {
+    use chess::game::*;
+
+    let mut board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"));
+    let mut moves = get_possible_moves(&board);
+    assert_eq!(moves.len(), 48);
+
+    let castles: Vec<_> = moves.iter().filter(|possible_move| possible_move.move_type == MoveType::Castle).map(Move::as_long_an).collect();
+    assert_eq!(castles, ["e1g1", "e1c1"]);
+
+    let first_move = moves.remove(0);
+    assert_eq!(moves.len(), 47);
+    moves.insert(0, first_move);
+    assert_eq!(moves.iter().map(Move::as_long_an).collect::<Vec<_>>(), get_possible_moves(&board).iter().map(Move::as_long_an).collect::<Vec<_>>());
+
+    moves.retain(is_noisy);
+    assert_eq!(moves.len(), get_capture_moves(&board).len());
+    assert_eq!(moves.into_iter().count(), 8);
+
+    // pinned pieces keep to the line between the king and the pinner
+    let position_info = get_position_info(&Board::from_fen(String::from("4k3/8/8/8/1b6/8/3N4/r2RK3 w - - 0 1")), White);
+    assert_eq!(position_info.pinned_pieces.len(), 2);
+    assert_eq!(position_info.pinned_pieces.get_safe_squares(D2), square_bitboard(D2) | square_bitboard(C3) | square_bitboard(B4));
+    assert_eq!(position_info.pinned_pieces.get_safe_squares(E1), !0);
+
+    // the most legal moves a standard position can have still fit on the stack,
+    // captures and evasions are only ever part of them
+    for fen in [
+        "R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1",
+        "3Q4/1Q4Q1/4Q3/2Q4R/Q4Q2/3Q4/1Q4Rp/1K1BBNNk w - - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+    ] {
+        board = Board::from_fen(String::from(fen));
+        moves = get_possible_moves(&board);
+        assert!(moves.len() <= 218);
+        assert!(!moves.spilled());
+        assert!(!get_capture_moves(&board).spilled() && !get_quiet_moves(&board).spilled());
+    }
+
+    assert_eq!(get_possible_moves(&Board::from_fen(String::from("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1"))).len(), 218);
+
+    // a full crazyhouse pocket has more moves than fit on the stack
+    board = Board::from_fen_variant("4k3/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1", Variant::Crazyhouse);
+    moves = get_possible_moves(&board);
+    assert_eq!(moves.len(), 301);
+    assert!(moves.spilled());
+    assert_eq!(moves.iter().filter(|possible_move| matches!(possible_move.move_type, MoveType::Drop(_))).count(), 296);
+    assert_eq!(get_num_moves(&mut board, 2), 75840);
+
+    moves.retain(|possible_move| possible_move.moved_piece == WhiteKing);
+    assert_eq!(moves.len(), 5);
+}
+
+#[test]
+fn static_exchange() {
+    use chess::game::*;
+
+    for (fen, long_an, expected) in [
+        ("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 100),
+        // the queen behind the bishop and the one behind the rook both join in
+        ("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", -220),
+        ("4k3/8/8/3q4/4P3/2N5/8/3RK3 w - - 0 1", "c3d5", 960),
+        ("4k3/8/8/8/8/8/1p6/R3K3 b Q - 0 1", "b2b1q", -100),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5d6", 100),
+        ("4k3/3r4/3r4/8/8/3R4/3R4/3QK3 w - - 0 1", "d3d6", 530),
+        ("4k3/4p3/3p4/8/8/8/8/3RK3 w - - 0 1", "d1d6", -430),
+        ("4k3/8/8/4r3/8/8/8/3RK3 w - - 0 1", "d1d2", 0),
+        ("4k3/8/8/2r5/8/8/8/3RK3 w - - 0 1", "d1c1", -530),
+        // the king can't take a defended piece
+        ("3rk3/8/4K3/3p4/8/8/8/8 w - - 0 1", "e6d5", -19900),
+        ("4k3/8/4K3/3p4/8/8/8/8 w - - 0 1", "e6d5", 100)
+    ] {
+        let board = Board::from_fen(String::from(fen));
+        let exchange_move = Move::from_long_an(long_an, &board);
+
+        assert_eq!(see(&board, &exchange_move), expected, "{} {}", fen, long_an);
+        assert!(see_ge(&board, &exchange_move, expected));
+        assert!(!see_ge(&board, &exchange_move, expected + 1));
+    }
+
+    // the rook it takes and the queen it becomes
+    let board = Board::from_fen(String::from("4k3/8/8/8/8/8/1p6/R3K3 b Q - 0 1"));
+    let promotion = get_capture_moves(&board).iter().copied().find(|capture| capture.move_type == MoveType::Promotion(BlackQueen) && capture.end_square == A1).unwrap();
+    assert_eq!(see(&board, &promotion), 530 + 960 - 100);
+
+    // atomic captures take out the neighbours instead of being taken back
+    let board = Board::from_fen_variant("4k3/8/8/8/2nrb3/8/3Q4/4K3 w - - 0 1", Variant::Atomic);
+    let capture = Move::from_long_an("d2d4", &board);
+    assert_eq!(see(&board, &capture), 530 + 320 + 330 - 960);
+    assert!(see_ge(&board, &capture, 0));
+}
+
+#[test]
+fn attack_queries() {
+    use chess::game::*;
+
+    // knight and rook both on the king
+    let board = Board::from_fen(String::from("4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1"));
+    assert!(board.is_in_check());
+    assert_eq!(board.checkers(), square_bitboard(E8) | square_bitboard(D3));
+    assert_eq!(board.attackers_to(F2, Black), square_bitboard(D3));
+    assert_eq!(board.attackers_to(E4, Black), square_bitboard(E8));
+    assert_eq!(board.attackers_to(D2, White), square_bitboard(E1));
+
+    let board = Board::from_fen(String::from("4k3/8/8/8/1b6/8/3N4/r2RK3 w - - 0 1"));
+    assert!(!board.is_in_check());
+    assert_eq!(board.checkers(), 0);
+    assert_eq!(board.pinned_pieces(White), square_bitboard(D2) | square_bitboard(D1));
+    assert_eq!(board.pinned_pieces(Black), 0);
+
+    // two pieces in the way is no pin
+    let board = Board::from_fen(String::from("4k3/8/8/8/1b6/2P5/3N4/4K3 w - - 0 1"));
+    assert_eq!(board.pinned_pieces(White), 0);
+
+    let board = Board::from_fen(String::from("4k3/8/8/8/8/8/4B3/R3K2R w KQ - 0 1"));
+    for (long_an, check) in [("e1c1", false), ("a1a8", true), ("h1h8", true), ("e2b5", true), ("e2f3", false)] {
+        assert_eq!(board.gives_check(&Move::from_long_an(long_an, &board)), check, "{}", long_an);
+    }
+
+    // the bishop uncovers the rook
+    let board = Board::from_fen(String::from("4k3/8/8/8/8/8/4B3/4R1K1 w - - 0 1"));
+    assert!(board.gives_check(&Move::from_long_an("e2d3", &board)));
+
+    // atomic captures blow up the capturer and whatever was in the way
+    for (fen, long_an, standard_check, atomic_check) in [
+        ("3k4/8/8/3n4/8/8/8/3RK3 w - - 0 1", "d1d5", true, false),
+        ("4k3/8/8/4n3/2N5/8/8/K3R3 w - - 0 1", "c4e5", false, true)
+    ] {
+        let board = Board::from_fen(String::from(fen));
+        assert_eq!(board.gives_check(&Move::from_long_an(long_an, &board)), standard_check, "{}", long_an);
+        let board = Board::from_fen_variant(fen, Variant::Atomic);
+        assert_eq!(board.gives_check(&Move::from_long_an(long_an, &board)), atomic_check, "{}", long_an);
+    }
+
+    // touching kings hide the bishop's check until they step apart
+    let board = Board::from_fen_variant("8/8/8/8/3kK3/8/8/6B1 w - - 0 1", Variant::Atomic);
+    assert!(!is_in_check(&board, Black));
+    assert_eq!(get_checkers(&board, Black), 0);
+    assert!(board.gives_check(&Move::from_long_an("e4f5", &board)));
+    assert!(!board.gives_check(&Move::from_long_an("e4e5", &board)));
+}
+
+#[test]
+fn perft_tooling() {
+    use chess::game::*;
+
+    let board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"));
+    let divide = perft_divide(&board, 3, &PerftOptions::default());
+
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+
+    let castle = divide.iter().find(|(root_move, _)| root_move.as_uci(false) == "e1g1").unwrap();
+    assert_eq!(castle.1, 2059);
+
+    // every way of counting has to agree move for move
+    for options in [
+        PerftOptions {threads: 4, hashed: false},
+        PerftOptions {threads: 1, hashed: true},
+        PerftOptions {threads: 3, hashed: true}
+    ] {
+        let other_divide = perft_divide(&board, 3, &options);
+        assert!(divide.iter().zip(&other_divide).all(|((a, a_nodes), (b, b_nodes))| a.as_uci(false) == b.as_uci(false) && a_nodes == b_nodes), "{:?}", options);
+        assert_eq!(perft(&board, 4, &options), 4085603, "{:?}", options);
+    }
+
+    assert_eq!(perft(&board, 0, &PerftOptions::default()), 1);
+    assert!(perft_divide(&board, 0, &PerftOptions::default()).is_empty());
+
+    let board = Board::new_variant(Variant::Crazyhouse);
+    assert_eq!(perft(&board, 3, &PerftOptions {threads: 2, hashed: true}), 8902);
+}
+
+#[test]
+fn hash_table() {
+    use chess::hash::HashTable;
+
+    // everything lands in the same bucket of four
+    let mut table: HashTable<u32, 4, 4> = HashTable::new();
+
+    for idx in 1..=4u64 {
+        table.set(idx << 4 | 1, idx as u32 * 10);
+    }
+
+    for idx in 1..=4u64 {
+        assert_eq!(table.get(idx << 4 | 1).copied(), Some(idx as u32 * 10));
+    }
+
+    table.set(3 << 4 | 1, 99);
+    assert_eq!(table.get(3 << 4 | 1).copied(), Some(99));
+    assert_eq!(table.get(1 << 4 | 1).copied(), Some(10));
+
+    // a fifth pushes out the oldest
+    table.set(5 << 4 | 1, 50);
+    assert_eq!(table.get(1 << 4 | 1), None);
+    assert_eq!(table.get(5 << 4 | 1).copied(), Some(50));
+    assert_eq!(table.get(2 << 4 | 1).copied(), Some(20));
+
+    table.clear();
+    assert_eq!(table.get(2 << 4 | 1), None);
+}
+
+#[test]
+fn packed_moves() {
+    use chess::game::*;
+
+    assert_eq!(std::mem::size_of::<PackedMove>(), 2);
+
+    for_each_fixture_move(|board, possible_move| {
+
+        let unpacked = possible_move.pack().unpack(board);
+
+        assert_eq!(unpacked.as_uci(board.chess960), possible_move.as_uci(board.chess960), "{}", board.get_fen());
+        assert_eq!(unpacked.move_type, possible_move.move_type, "{}", board.get_fen());
+        assert_eq!(unpacked.moved_piece, possible_move.moved_piece, "{}", board.get_fen());
+        assert_eq!(unpacked.replaced_piece, possible_move.replaced_piece, "{}", board.get_fen());
+        assert_eq!(PackedMove::from(&unpacked), possible_move.pack());
+    });
+
+    let mut board = Board::new_variant(Variant::Standard);
+    let null_move = Move::new_null(&board).pack();
+    assert!(null_move.is_null());
+    assert_eq!(null_move.unpack(&board).move_type, MoveType::Null);
+
+    let e4 = Move::from_long_an("e2e4", &board).pack();
+    assert_eq!((e4.start_square(), e4.end_square()), (an_to_square(String::from("e2")), an_to_square(String::from("e4"))));
+
+    // the state a move overwrites comes back from the undo stack
+    let fen = board.get_fen();
+    let hash = board.hash();
+
+    for long_an in ["e2e4", "d7d5", "e4d5", "g8f6"] {
+        board.make_move(&Move::from_long_an(long_an, &board));
+    }
+
+    assert_eq!(board.previous_moves().map(Move::as_long_an).collect::<Vec<_>>(), ["e2e4", "d7d5", "e4d5", "g8f6"]);
+    assert_eq!(board.last_move().unwrap().as_long_an(), "g8f6");
+
+    while board.undo_move().is_some() {}
+
+    assert_eq!(board.get_fen(), fen);
+    assert_eq!(board.hash(), hash);
+    assert!(board.last_move().is_none());
+}
+
+#[test]
+fn san() {
+    use chess::game::*;
+
+    let san_of = |fen: &str, long_an: &str| {
+        let board = Board::from_fen(String::from(fen));
+        board.san(&Move::from_long_an(long_an, &board))
+    };
+
+    // disambiguation by file, then rank, then both
+    let queens = "8/8/8/7k/8/Q7/8/Q1Q4K w - - 0 1";
+    assert_eq!(san_of(queens, "a1b2"), "Qa1b2");
+    assert_eq!(san_of(queens, "c1b2"), "Qcb2");
+    assert_eq!(san_of(queens, "a3b2"), "Q3b2");
+    assert_eq!(san_of(queens, "a3a2"), "Q3a2");
+    assert_eq!(san_of(queens, "c1c2"), "Qc2");
+
+    let knights = "rnbqkb1r/ppp1pppp/5n2/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 3 3";
+    assert_eq!(san_of(knights, "b8d7"), "Nbd7");
+    assert_eq!(san_of(knights, "f6d7"), "Nfd7");
+
+    // check and mate
+    assert_eq!(san_of("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "h5f7"), "Qxf7#");
+    assert_eq!(san_of("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e7e8q"), "e8=Q+");
+    assert_eq!(san_of("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e7e8n"), "e8=N");
+    assert_eq!(san_of("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", "e4d3"), "exd3");
+    assert_eq!(san_of("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e1c1"), "O-O-O");
+
+    // sloppy forms
+    let parse = |fen: &str, san: &str| Board::from_fen(String::from(fen)).parse_san(san).map(|parsed_move| parsed_move.as_long_an());
+
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e8Q"), Ok(String::from("e7e8q")));
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e8=n+"), Ok(String::from("e7e8n")));
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e8(R)"), Ok(String::from("e7e8r")));
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e8"), Err(SanError::MissingPromotion));
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e7e8+"), Err(SanError::MissingPromotion));
+    assert_eq!(parse("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e8=K"), Err(SanError::BadPromotion('K')));
+    assert_eq!(parse("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", "exd3e.p."), Ok(String::from("e4d3")));
+    assert_eq!(parse("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", "exd3 e.p."), Ok(String::from("e4d3")));
+    assert_eq!(parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "0-0"), Ok(String::from("e1g1")));
+    assert_eq!(parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "O-O-O+"), Ok(String::from("e1c1")));
+    assert_eq!(parse(knights, "Nbd7"), Ok(String::from("b8d7")));
+    assert_eq!(parse(knights, "Nb8-d7"), Ok(String::from("b8d7")));
+    assert_eq!(parse(knights, "Nd7"), Err(SanError::AmbiguousMove));
+    assert_eq!(parse(START_POS, "Ng1f3"), Ok(String::from("g1f3")));
+    assert_eq!(parse(START_POS, "e2e4"), Ok(String::from("e2e4")));
+    assert_eq!(parse(START_POS, "Nf3!?"), Ok(String::from("g1f3")));
+    assert_eq!(parse(START_POS, ""), Err(SanError::Empty));
+    assert_eq!(parse(START_POS, "Zf3"), Err(SanError::BadPiece('Z')));
+    assert_eq!(parse(START_POS, "Nz9"), Err(SanError::BadSquare));
+    assert_eq!(parse(START_POS, "e"), Err(SanError::BadSquare));
+    assert_eq!(parse(START_POS, "x"), Err(SanError::BadSquare));
+    assert_eq!(parse(START_POS, "Nf"), Err(SanError::BadSquare));
+    assert_eq!(parse(START_POS, "N%f3"), Err(SanError::UnexpectedChar('%')));
+    assert_eq!(parse(START_POS, "Ke2"), Err(SanError::IllegalMove));
+    assert_eq!(parse(START_POS, "O-O"), Err(SanError::IllegalMove));
+    assert_eq!(parse(START_POS, "--"), Ok(String::from("0000")));
+
+    // every legal move comes back from its own san
+    for_each_fixture_move(|board, possible_move| {
+        let san = board.san(possible_move);
+        assert_eq!(board.parse_san(&san).map(|parsed_move| parsed_move.pack()), Ok(possible_move.pack()), "{} {}", board.get_fen(), san);
+    });
+}
+
+#[test]
+fn pgn() {
+    use chess::game::*;
+
+    let pgn = "1. e4 e5 (1... c5 2. Nf3 (2. Nc3 Nc6) 2... d6) 2. Nf3 Nc6 3. Bc4 Nf6 4. Ng5 d5 5. exd5 Nxd5 6. Nxf7 Kxf7 7. Qf3+ Ke6 8. Nc3 Nb4 9. O-O c6 1-0";
+    let game = Game::from_pgn(format!("[Event \"?\"]\n[Result \"1-0\"]\n\n{}", pgn)).unwrap();
+
+    assert_eq!(game.result, GameResult::Win);
+    assert_eq!(game.to_pgn(), pgn);
+
+    // the knight on d5 is pinned, so there's nothing to tell apart
+    let game = Game::from_pgn(pgn.replace("Nb4", "Ncb4").replace("2... d6", "d6")).unwrap();
+    assert_eq!(game.to_pgn(), pgn);
+
+    let first_move = game.get_root();
+    assert_eq!(first_move.played_move.as_long_an(), "e2e4");
+    assert_eq!(game.get_alternatives(game.get_main_line(first_move).unwrap())[0].played_move.as_long_an(), "c7c5");
+
+    // sloppy san still comes out exact
+    let game = Game::from_pgn(String::from("1. e2e4 e7e5 2. Ng1f3 Nb8-c6 3. Bb5 a6 4. Bxc6 dxc6 5. 0-0 *")).unwrap();
+    assert_eq!(game.result, GameResult::Unknown);
+    assert_eq!(game.to_pgn(), "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Bxc6 dxc6 5. O-O *");
+
+    assert_eq!(Game::from_pgn(String::from("1. e4 e5 2. Nf3 1/2-1/2")).unwrap().result, GameResult::Draw);
+
+    // a move that isn't there gives the san error back
+    assert_eq!(Game::from_pgn(String::from("1. e4 e5 2. Ke3 *")).err(), Some(PgnError::BadSan(SanError::IllegalMove)));
+    assert_eq!(Game::from_pgn(String::from("1. e4 (1. Zf3) e5 *")).err(), Some(PgnError::BadSan(SanError::BadPiece('Z'))));
+
+    // variants and set up positions keep the tags they need to be played back
+    let pgn = "[Variant \"Crazyhouse\"]\n\n1. e4 d5 2. exd5 Qxd5 3. P@e4 *";
+    let game = Game::from_pgn(String::from(pgn)).unwrap();
+    assert_eq!(game.get_variant(), Variant::Crazyhouse);
+    assert_eq!(game.to_pgn(), pgn);
+
+    let pgn = "[SetUp \"1\"]\n[FEN \"8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1\"]\n\n1... exd3 2. Kd2 *";
+    let game = Game::from_pgn(String::from(pgn)).unwrap();
+    assert_eq!(game.get_start_board().get_fen(), "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+    assert_eq!(game.to_pgn(), pgn);
+    assert_eq!(Game::from_pgn(game.to_pgn()).unwrap().to_pgn(), pgn);
+
+    let pgn = "[Variant \"Three-check\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 3+3 0 1\"]\n\n1. Ra8+ Kd7 *";
+    assert_eq!(Game::from_pgn(String::from(pgn)).unwrap().to_pgn(), pgn);
+
+    assert_eq!(Game::from_pgn(String::from("[Variant \"Antichess\"]\n\n1. e3 *")).err(), Some(PgnError::UnknownVariant));
+    assert_eq!(Game::from_pgn(String::from("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n\n*")).err(), Some(PgnError::BadFen(FenError::MissingKing(White))));
+}
+
+#[test]
+fn uci_moves() {
+    use chess::game::*;
+
+    let board = Board::from_fen(String::from(START_POS));
+
+    assert_eq!(board.parse_uci_move("e2e4").unwrap().move_type, MoveType::PawnDouble);
+    assert_eq!(board.parse_uci_move(" g1f3\n").unwrap().as_long_an(), "g1f3");
+    assert_eq!(board.parse_uci_move("0000").unwrap().move_type, MoveType::Null);
+    assert_eq!(board.parse_uci_move("e2e5").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("e1g1").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("e2").err(), Some(MoveParseError::BadLength(2)));
+    assert_eq!(board.parse_uci_move("").err(), Some(MoveParseError::BadLength(0)));
+    assert_eq!(board.parse_uci_move("e2e4qq").err(), Some(MoveParseError::BadLength(6)));
+    assert_eq!(board.parse_uci_move("z2e4").err(), Some(MoveParseError::BadSquare));
+    assert_eq!(board.parse_uci_move("e2e9").err(), Some(MoveParseError::BadSquare));
+    assert_eq!(board.parse_uci_move("e2e4q").err(), Some(MoveParseError::IllegalMove));
+
+    // no passing out of check
+    let board = Board::from_fen(String::from("4k3/8/8/8/8/8/8/4K2r w - - 0 1"));
+    assert_eq!(board.parse_uci_move("0000").err(), Some(MoveParseError::IllegalMove));
+    assert!(board.parse_uci_move("e1e2").is_ok());
+
+    // promotions are lowercase but belong to whoever is moving
+    let board = Board::from_fen(String::from("8/4P3/8/7k/8/8/1p6/7K w - - 0 1"));
+    assert_eq!(board.parse_uci_move("e7e8q").unwrap().move_type, MoveType::Promotion(WhiteQueen));
+    assert_eq!(board.parse_uci_move("e7e8N").unwrap().move_type, MoveType::Promotion(WhiteKnight));
+    assert_eq!(board.parse_uci_move("e7e8").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("e7e8k").err(), Some(MoveParseError::BadPromotion('k')));
+    assert_eq!(board.parse_uci_move("e7e8p").err(), Some(MoveParseError::BadPromotion('p')));
+    assert_eq!(Move::from_long_an("e7e8q", &board).move_type, MoveType::Promotion(WhiteQueen));
+    assert_eq!(Move::from_long_an("e7e8", &board).move_type, MoveType::Promotion(WhiteQueen));
+
+    let board = Board::from_fen(String::from("8/8/8/7k/8/8/1p6/R3K3 b - - 0 1"));
+    assert_eq!(board.parse_uci_move("b2a1r").unwrap().move_type, MoveType::Promotion(BlackRook));
+    assert_eq!(Move::from_long_an("b2a1r", &board).move_type, MoveType::Promotion(BlackRook));
+    assert_eq!(Move::from_long_an("b2b1n", &board).move_type, MoveType::Promotion(BlackKnight));
+
+    // castles as the king's two step or the king taking its rook, only the second in chess960
+    let mut board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"));
+
+    for (uci, end_square) in [("e1g1", "h1"), ("e1h1", "h1"), ("e1c1", "a1"), ("e1a1", "a1")] {
+        let castle = board.parse_uci_move(uci).unwrap();
+        assert_eq!((castle.move_type, square_to_an(castle.end_square)), (MoveType::Castle, String::from(end_square)));
+    }
+
+    board.chess960 = true;
+    assert_eq!(board.parse_uci_move("e1g1").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("e1h1").unwrap().move_type, MoveType::Castle);
+
+    let board = Board::from_fen(String::from("1rk5/8/8/8/8/8/8/1RK3R1 w GBb - 0 1"));
+    assert_eq!(board.parse_uci_move("c1g1").unwrap().move_type, MoveType::Castle);
+    assert_eq!(board.parse_uci_move("c1b1").unwrap().move_type, MoveType::Castle);
+    assert_eq!(board.parse_uci_move("c1d1").unwrap().move_type, MoveType::Normal);
+
+    // drops
+    let board = Board::from_fen_variant("r1bqkbnr/ppp2ppp/2n5/3pp3/4P3/5N2/PPPP1PPP/RNBQKB1R[Pnb] w KQkq - 0 4", Variant::Crazyhouse);
+    assert_eq!(board.parse_uci_move("P@e3").unwrap().move_type, MoveType::Drop(WhitePawn));
+    assert_eq!(board.parse_uci_move("p@e3").unwrap().move_type, MoveType::Drop(WhitePawn));
+    assert_eq!(board.parse_uci_move("N@e3").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("P@e8").err(), Some(MoveParseError::IllegalMove));
+    assert_eq!(board.parse_uci_move("K@e3").err(), Some(MoveParseError::BadPiece('K')));
+    assert_eq!(board.parse_uci_move("P@e0").err(), Some(MoveParseError::BadSquare));
+
+    // every legal move comes back from its own uci
+    for_each_fixture_move(|board, possible_move| {
+        let uci = possible_move.as_uci(board.chess960);
+        assert_eq!(board.parse_uci_move(&uci).map(|parsed_move| parsed_move.pack()), Ok(possible_move.pack()), "{} {}", board.get_fen(), uci);
+    });
+}
+
+#[test]
+fn move_formats() {
+    use chess::game::*;
+
+    let format = |fen: &str, long_an: &str, format: MoveFormat| {
+        let board = Board::from_fen(String::from(fen));
+        board.format_move(&Move::from_long_an(long_an, &board), format)
+    };
+
+    let parse = |fen: &str, text: &str, format: MoveFormat| {
+        Board::from_fen(String::from(fen)).parse_move(text, format).map(|parsed_move| parsed_move.as_long_an())
+    };
+
+    let scandinavian = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    let promotion = "8/4P3/8/7k/8/8/8/K7 w - - 0 1";
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    assert_eq!(format(START_POS, "g1f3", MoveFormat::Lan), "Ng1-f3");
+    assert_eq!(format(START_POS, "e2e4", MoveFormat::Lan), "e2-e4");
+    assert_eq!(format(scandinavian, "e4d5", MoveFormat::Lan), "e4xd5");
+    assert_eq!(format(promotion, "e7e8q", MoveFormat::Lan), "e7-e8=Q+");
+    assert_eq!(format(kiwipete, "e1g1", MoveFormat::Lan), "O-O");
+
+    assert_eq!(format(START_POS, "g1f3", MoveFormat::Figurine), "♘f3");
+    assert_eq!(format("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", "g8f6", MoveFormat::Figurine), "♞f6");
+    assert_eq!(format(promotion, "e7e8q", MoveFormat::Figurine), "e8=♕+");
+    assert_eq!(format(kiwipete, "e2a6", MoveFormat::Figurine), "♗xa6");
+
+    assert_eq!(format(START_POS, "g1f3", MoveFormat::Iccf), "7163");
+    assert_eq!(format(promotion, "e7e8q", MoveFormat::Iccf), "57581");
+    assert_eq!(format(promotion, "e7e8n", MoveFormat::Iccf), "57584");
+    assert_eq!(format(kiwipete, "e1g1", MoveFormat::Iccf), "5171");
+    assert_eq!(format(kiwipete, "e1c1", MoveFormat::Iccf), "5131");
+    assert_eq!(format("1rk5/8/8/8/8/8/8/1RK3R1 w GBb - 0 1", "c1g1", MoveFormat::Iccf), "3171");
+
+    assert_eq!(parse(START_POS, "Ng1-f3", MoveFormat::Lan), Ok(String::from("g1f3")));
+    assert_eq!(parse(scandinavian, "e4xd5", MoveFormat::Lan), Ok(String::from("e4d5")));
+    assert_eq!(parse(START_POS, "♘f3", MoveFormat::Figurine), Ok(String::from("g1f3")));
+    assert_eq!(parse(START_POS, "♞f3", MoveFormat::Figurine), Ok(String::from("g1f3")));
+    assert_eq!(parse(promotion, "e8=♕+", MoveFormat::Figurine), Ok(String::from("e7e8q")));
+    assert_eq!(parse(START_POS, "7163", MoveFormat::Iccf), Ok(String::from("g1f3")));
+    assert_eq!(parse(promotion, "57582", MoveFormat::Iccf), Ok(String::from("e7e8r")));
+    assert_eq!(parse(kiwipete, "5171", MoveFormat::Iccf), Ok(String::from("e1g1")));
+
+    assert_eq!(parse(START_POS, "Ng1-f4", MoveFormat::Lan), Err(MoveParseError::BadSan(SanError::IllegalMove)));
+    assert_eq!(parse(START_POS, "♔e2", MoveFormat::Figurine), Err(MoveParseError::BadSan(SanError::IllegalMove)));
+    assert_eq!(parse(START_POS, "9163", MoveFormat::Iccf), Err(MoveParseError::BadSquare));
+    assert_eq!(parse(promotion, "57585", MoveFormat::Iccf), Err(MoveParseError::BadPromotion('5')));
+    assert_eq!(parse(START_POS, "716", MoveFormat::Iccf), Err(MoveParseError::BadLength(3)));
+    assert_eq!(parse(START_POS, "7164", MoveFormat::Iccf), Err(MoveParseError::IllegalMove));
+
+    // every legal move comes back from every format
+    for_each_fixture_move(|board, possible_move| {
+        for move_format in MoveFormat::ALL {
+            let text = board.format_move(possible_move, move_format);
+            assert_eq!(board.parse_move(&text, move_format).map(|parsed_move| parsed_move.pack()), Ok(possible_move.pack()), "{} {}", board.get_fen(), text);
+        }
+    });
+}
+
+#[test]
+fn move_queries() {
+    use chess::game::*;
+
+    let board = Board::from_fen(String::from("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1"));
+    let en_passant = Move::from_long_an("e4d3", &board);
+
+    assert!(en_passant.is_capture() && !en_passant.is_quiet() && !en_passant.is_promotion());
+    assert_eq!((en_passant.replaced_piece, en_passant.captured_piece()), (Empty, WhitePawn));
+    assert!(en_passant.is_irreversible(&board));
+    assert_eq!(en_passant.see(&board), 100);
+
+    let board = Board::from_fen(String::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"));
+    let query = |long_an: &str| Move::from_long_an(long_an, &board);
+
+    let castle = query("e1g1");
+    assert!(castle.is_castle() && !castle.is_capture() && castle.is_quiet() && castle.is_irreversible(&board));
+    assert_eq!(castle.captured_piece(), Empty);
+    assert_eq!(castle.see(&board), 0);
+
+    let capture = query("e2a6");
+    assert!(capture.is_capture() && !capture.is_castle() && !capture.is_quiet());
+    assert_eq!(capture.captured_piece(), BlackBishop);
+    assert_eq!(capture.see(&board), see(&board, &capture));
+
+    // king and rook moves that give up a castling right can't be undone either
+    for (long_an, irreversible) in [("a2a3", true), ("c3b1", false), ("f3f5", false), ("e1d1", true), ("a1b1", true), ("h1f1", true), ("e5d3", false)] {
+        assert_eq!(query(long_an).is_irreversible(&board), irreversible, "{}", long_an);
+    }
+
+    let board = Board::from_fen(String::from("8/4P3/8/7k/8/8/8/K7 w - - 0 1"));
+    let promotion = Move::from_long_an("e7e8q", &board);
+    assert!(promotion.is_promotion() && !promotion.is_capture() && !promotion.is_quiet());
+    assert_eq!(promotion.promotion_piece(), Some(WhiteQueen));
+    assert!(promotion.gives_check(&board));
+    assert!(!Move::from_long_an("e7e8n", &board).gives_check(&board));
+    assert_eq!(Move::from_long_an("a1a2", &board).promotion_piece(), None);
+
+    let null_move = Move::new_null(&board);
+    assert!(null_move.is_null() && !null_move.gives_check(&board) && !null_move.is_irreversible(&board));
+
+    let board = Board::from_fen_variant("r1bqkbnr/ppp2ppp/2n5/3pp3/4P3/5N2/PPPP1PPP/RNBQKB1R[Pnb] w KQkq - 0 4", Variant::Crazyhouse);
+    let drop = board.parse_uci_move("P@e3").unwrap();
+    assert!(drop.is_drop() && drop.is_quiet() && drop.is_irreversible(&board));
+
+    // a check uses one up in three-check
+    let fen = "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3";
+    let check = |board: &Board| Move::from_long_an("f1b5", board);
+    let board = Board::from_fen(String::from(fen));
+    assert!(!check(&board).is_irreversible(&board));
+    let board = Board::from_fen_variant(fen, Variant::ThreeCheck);
+    assert!(check(&board).is_irreversible(&board));
+
+    // irreversible is exactly whatever resets the clock, takes a castling right or a check, or empties a pocket
+    for_each_fixture_move(|board, possible_move| {
+
+        let fen = board.get_fen();
+        let irreversible = possible_move.is_irreversible(board);
+        let gives_check = possible_move.gives_check(board);
+        let see_score = possible_move.see(board);
+        let castling_rights = board.castling_rights;
+        let remaining_checks = board.get_remaining_checks(board.side_to_move);
+
+        board.make_move(possible_move);
+
+        let expected = board.halfmove_clock == 0
+            || board.castling_rights != castling_rights
+            || board.get_remaining_checks(board.side_to_move.opposite()) != remaining_checks
+            || possible_move.is_drop();
+
+        assert_eq!(irreversible, expected, "{} {}", fen, possible_move.as_long_an());
+        assert_eq!(gives_check, board.is_in_check(), "{} {}", fen, possible_move.as_long_an());
+        board.undo_move();
+
+        assert_eq!(possible_move.is_quiet(), !is_noisy(possible_move));
+        assert_eq!(see_score, see(board, possible_move));
+    });
+}